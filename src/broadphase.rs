@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+
+use crate::hitbox::Hitbox;
+
+//width and height of a grid cell; about the size of a regular enemy, so most objects span few cells
+const CELL_SIZE: f32 = 64.0;
+
+/// The kinds of object indexed by the broadphase. Only some kinds are ever tested against each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layer {
+    Player,
+    Enemies,
+    PlayerBullets,
+    EnemyBullets,
+}
+
+const LAYERS: usize = 4;
+
+/// Uniform grid over the world that finds which objects might be touching, so the exact hitbox
+/// tree tests only run on nearby pairs. Rebuilt from scratch every tick.
+#[derive(Debug, Default, Clone)]
+pub struct Broadphase {
+    //bounds of every inserted object, per layer, by the index it was inserted under
+    bounds: [Vec<Hitbox>; LAYERS],
+    //for every occupied cell, the objects of each layer overlapping it
+    cells: HashMap<(i32, i32), [Vec<usize>; LAYERS]>,
+}
+
+impl Broadphase {
+    pub fn new() -> Broadphase {
+        Broadphase::default()
+    }
+
+    //forget every object; cells are dropped too, so `pairs` only ever walks occupied ones
+    pub fn clear(&mut self) {
+        for bounds in &mut self.bounds {
+            bounds.clear();
+        }
+        self.cells.clear();
+    }
+
+    /// Index the objects of `layer` by their bounds, numbered in order from 0.
+    pub fn insert_all(&mut self, layer: Layer, bounds: impl IntoIterator<Item = Hitbox>) {
+        for hitbox in bounds {
+            let index = self.bounds[layer as usize].len();
+            let min = cell_of(hitbox.point.x, hitbox.point.y);
+            let max = cell_of(hitbox.point.x + hitbox.size.x, hitbox.point.y + hitbox.size.y);
+            for x in min.0..=max.0 {
+                for y in min.1..=max.1 {
+                    self.cells.entry((x, y)).or_default()[layer as usize].push(index);
+                }
+            }
+            self.bounds[layer as usize].push(hitbox);
+        }
+    }
+
+    /// Every pair of an `a` object and a `b` object whose bounds overlap, as their indices,
+    /// sorted so the result does not depend on the grid's layout.
+    pub fn pairs(&self, a: Layer, b: Layer) -> Vec<(usize, usize)> {
+        let (a_bounds, b_bounds) = (&self.bounds[a as usize], &self.bounds[b as usize]);
+        let mut pairs = Vec::new();
+        for cell in self.cells.values() {
+            for &i in &cell[a as usize] {
+                for &j in &cell[b as usize] {
+                    if a_bounds[i].collides_with(&b_bounds[j]) {
+                        pairs.push((i, j));
+                    }
+                }
+            }
+        }
+        //objects spanning several cells are found once in each of them
+        pairs.sort_unstable();
+        pairs.dedup();
+        pairs
+    }
+}
+
+//the grid cell containing the point (x, y)
+fn cell_of(x: f32, y: f32) -> (i32, i32) {
+    ((x / CELL_SIZE).floor() as i32, (y / CELL_SIZE).floor() as i32)
+}
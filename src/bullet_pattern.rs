@@ -0,0 +1,192 @@
+use ggez::glam::Vec2;
+use serde::Deserialize;
+use std::f32::consts::TAU;
+
+/// A timed bullet-hell firing pattern. Each variant is a pure function of time, emitter and
+/// target, so the same inputs always fire the same bullets.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum BulletPattern {
+    //`count` bullets evenly spaced around a full circle, every `period` ticks
+    Radial { count: u32, period: u32 },
+    //`arms` evenly spaced streams that turn by `spin` radians with every shot, one shot every `period` ticks
+    Spiral { arms: u32, period: u32, spin: f32 },
+    //`count` bullets spread over `spread` radians around the direction of the target, every `period` ticks
+    AimedFan { count: u32, spread: f32, period: u32 },
+    //a dense stream, one bullet every `interval` ticks, that sweeps `sweep` radians to either side
+    //of straight down and back over `sweep_period` ticks
+    RotatingLaser { interval: u32, sweep: f32, sweep_period: u32 },
+    //every `period` ticks: wait `delay` ticks, then fire `volleys` aimed shots `gap` ticks apart
+    DelayedBurst { period: u32, delay: u32, volleys: u32, gap: u32 },
+}
+
+impl BulletPattern {
+    /// Directions (unit vectors) of the bullets fired at tick `time` from `emitter` towards `target`.
+    pub fn fire(&self, time: u32, emitter: Vec2, target: Vec2) -> Vec<Vec2> {
+        let aim = (target - emitter).try_normalize().unwrap_or(Vec2::Y);
+        match *self {
+            BulletPattern::Radial { count, period } if time.is_multiple_of(period) => ring(count, 0.0),
+            BulletPattern::Spiral { arms, period, spin } if time.is_multiple_of(period) => {
+                ring(arms, spin * (time / period) as f32)
+            }
+            BulletPattern::AimedFan { count, spread, period } if time.is_multiple_of(period) => {
+                fan(aim, count, spread)
+            }
+            BulletPattern::RotatingLaser { interval, sweep, sweep_period } if time.is_multiple_of(interval) => {
+                let angle = sweep * (TAU * time as f32 / sweep_period as f32).sin();
+                vec![Vec2::from_angle(angle).rotate(Vec2::Y)]
+            }
+            BulletPattern::DelayedBurst { period, delay, volleys, gap } => {
+                match (time % period).checked_sub(delay) {
+                    Some(t) if t.is_multiple_of(gap) && t / gap < volleys => vec![aim],
+                    _ => Vec::new(),
+                }
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    //patterns that fire no bullets, or time their shots by a zero period, interval or gap
+    pub fn validate(&self) -> Result<(), String> {
+        let valid = match *self {
+            BulletPattern::Radial { count, period } => count > 0 && period > 0,
+            BulletPattern::Spiral { arms, period, .. } => arms > 0 && period > 0,
+            BulletPattern::AimedFan { count, period, .. } => count > 0 && period > 0,
+            BulletPattern::RotatingLaser { interval, sweep_period, .. } => interval > 0 && sweep_period > 0,
+            BulletPattern::DelayedBurst { period, gap, .. } => period > 0 && gap > 0,
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(format!("bullet pattern {self:?} needs non-zero counts and periods"))
+        }
+    }
+}
+
+//`count` directions evenly spaced around a circle, the first at `offset` radians
+fn ring(count: u32, offset: f32) -> Vec<Vec2> {
+    (0..count)
+        .map(|i| Vec2::from_angle(offset + TAU * i as f32 / count as f32))
+        .collect()
+}
+
+//`count` directions spread evenly over `spread` radians, centered on `aim`
+fn fan(aim: Vec2, count: u32, spread: f32) -> Vec<Vec2> {
+    if count == 1 {
+        return vec![aim];
+    }
+    (0..count)
+        .map(|i| Vec2::from_angle(spread * (i as f32 / (count - 1) as f32 - 0.5)).rotate(aim))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-4;
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < EPSILON, "expected {b}, got {a}");
+    }
+
+    fn assert_units(directions: &[Vec2]) {
+        for direction in directions {
+            assert_close(direction.length(), 1.0);
+        }
+    }
+
+    //a set of directions spaced evenly around a circle cancels out
+    fn assert_balanced(directions: &[Vec2]) {
+        let sum: Vec2 = directions.iter().sum();
+        assert_close(sum.length(), 0.0);
+    }
+
+    #[test]
+    fn radial_fires_an_even_ring_on_its_period() {
+        let pattern = BulletPattern::Radial { count: 8, period: 10 };
+        let directions = pattern.fire(20, Vec2::ZERO, Vec2::new(0.0, 100.0));
+        assert_eq!(directions.len(), 8);
+        assert_units(&directions);
+        assert_balanced(&directions);
+        for pair in directions.windows(2) {
+            assert_close(pair[0].angle_between(pair[1]), TAU / 8.0);
+        }
+        assert!(pattern.fire(25, Vec2::ZERO, Vec2::ZERO).is_empty());
+    }
+
+    #[test]
+    fn spiral_turns_by_spin_with_every_shot() {
+        let pattern = BulletPattern::Spiral { arms: 3, period: 4, spin: 0.1 };
+        let first = pattern.fire(4, Vec2::ZERO, Vec2::ZERO);
+        let second = pattern.fire(8, Vec2::ZERO, Vec2::ZERO);
+        assert_eq!(first.len(), 3);
+        assert_eq!(second.len(), 3);
+        assert_units(&second);
+        assert_balanced(&second);
+        assert_close(Vec2::X.angle_between(first[0]), 0.1);
+        for (a, b) in first.iter().zip(&second) {
+            assert_close(a.angle_between(*b), 0.1);
+        }
+        assert!(pattern.fire(9, Vec2::ZERO, Vec2::ZERO).is_empty());
+    }
+
+    #[test]
+    fn aimed_fan_spreads_evenly_around_the_target() {
+        let pattern = BulletPattern::AimedFan { count: 5, spread: 1.0, period: 30 };
+        let (emitter, target) = (Vec2::new(100.0, 100.0), Vec2::new(200.0, 100.0));
+        let directions = pattern.fire(60, emitter, target);
+        assert_eq!(directions.len(), 5);
+        assert_units(&directions);
+        //outermost bullets are `spread` apart, the middle one flies straight at the target
+        assert_close(directions[0].angle_between(directions[4]), 1.0);
+        assert_close(directions[2].angle_between(Vec2::X), 0.0);
+        for i in 0..5 {
+            assert_close(Vec2::X.angle_between(directions[i]), -Vec2::X.angle_between(directions[4 - i]));
+        }
+        assert!(pattern.fire(61, emitter, target).is_empty());
+    }
+
+    #[test]
+    fn aimed_fan_of_one_fires_straight_at_the_target() {
+        let pattern = BulletPattern::AimedFan { count: 1, spread: 1.0, period: 1 };
+        let directions = pattern.fire(0, Vec2::ZERO, Vec2::new(0.0, -50.0));
+        assert_eq!(directions, vec![Vec2::NEG_Y]);
+    }
+
+    #[test]
+    fn rotating_laser_sweeps_evenly_to_either_side() {
+        let pattern = BulletPattern::RotatingLaser { interval: 1, sweep: 0.5, sweep_period: 60 };
+        let at = |time| {
+            let directions = pattern.fire(time, Vec2::ZERO, Vec2::ZERO);
+            assert_eq!(directions.len(), 1);
+            assert_units(&directions);
+            Vec2::Y.angle_between(directions[0])
+        };
+        assert_close(at(0), 0.0);
+        assert_close(at(15), 0.5);
+        assert_close(at(45), -0.5);
+        assert_close(at(10), -at(40));
+        let sparse = BulletPattern::RotatingLaser { interval: 3, sweep: 0.5, sweep_period: 60 };
+        assert!(sparse.fire(4, Vec2::ZERO, Vec2::ZERO).is_empty());
+    }
+
+    #[test]
+    fn delayed_burst_fires_its_volleys_after_the_delay() {
+        let pattern = BulletPattern::DelayedBurst { period: 100, delay: 10, volleys: 3, gap: 5 };
+        let (emitter, target) = (Vec2::ZERO, Vec2::new(30.0, 40.0));
+        let firing: Vec<u32> = (0..200).filter(|&time| !pattern.fire(time, emitter, target).is_empty()).collect();
+        assert_eq!(firing, vec![10, 15, 20, 110, 115, 120]);
+        let directions = pattern.fire(15, emitter, target);
+        assert_eq!(directions.len(), 1);
+        assert_close(directions[0].x, 0.6);
+        assert_close(directions[0].y, 0.8);
+    }
+
+    #[test]
+    fn zero_periods_are_rejected() {
+        assert!(BulletPattern::Radial { count: 8, period: 0 }.validate().is_err());
+        assert!(BulletPattern::DelayedBurst { period: 10, delay: 0, volleys: 1, gap: 0 }.validate().is_err());
+        assert!(BulletPattern::Spiral { arms: 2, period: 3, spin: 0.2 }.validate().is_ok());
+    }
+}
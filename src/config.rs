@@ -1,28 +1,162 @@
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::Path;
+
 // Display and world dimensions
 pub const DISPLAY_WIDTH: f32 = 920.0;
 pub const DISPLAY_HEIGHT: f32 = 690.0;
 pub const WORLD_WIDTH: f32 = 920.0;
 pub const WORLD_HEIGHT: f32 = 690.0;
 
-// Physics
-pub const FRICTION: f32 = 0.1;
-
-// Enemy wave layout
-pub const ENEMIES_PER_ROW: u8 = 7;
-pub const ENEMY_ROWS: u8 = 3;
-
-// Gameplay tuning
-pub const ENEMY_SHOOT_CHANCE: usize = 10;
-pub const NUM_STARS: usize = 300;
-pub const PLAYER_EXP_PER_KILL: f32 = 40.0;
-pub const PLAYER_INVINCIBILITY_FRAMES: u32 = 60;
-pub const PLAYER_CONTACT_DAMAGE: f32 = 20.0;
-pub const WAVE_GRACE_PERIOD: usize = 200;
-pub const PLAYER_MAX_HEALTH: f32 = 100.0;
-pub const PLAYER_MIN_HEALTH_RESTORE: f32 = 25.0;
-pub const EXP_TO_LEVEL: f32 = 100.0;
+// Timing
+//the simulation always advances in fixed steps of 1/TICKS_PER_SECOND, independent of refresh rate
+pub const TICKS_PER_SECOND: u32 = 60;
+//longest frame the update loop will try to catch up on, to avoid a spiral of death after a stall
+pub const MAX_FRAME_TIME: f32 = 0.25;
 
 // Debug
 pub const SHOW_FRAMERATE: bool = false;
 pub const SHOW_HITBOXES: bool = false;
 pub const HITBOX_COLOR: [f32; 4] = [1.0, 0.1, 0.1, 0.4];
+
+pub const CONFIG_FILE: &str = "config.json";
+
+/// Gameplay tuning values, loaded at startup from `resources/config.json`.
+/// Any key missing from the file keeps its default below.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    // Physics
+    pub friction: f32,
+
+    // Enemy wave layout
+    //upper limits for generated waves
+    pub enemies_per_row: u8,
+    pub enemy_rows: u8,
+
+    // Wave generation
+    //difficulty a generated stage may spend: `wave_budget + wave_budget_per_stage * stage`
+    pub wave_budget: f32,
+    pub wave_budget_per_stage: f32,
+    //extra enemy health per stage of generated waves, as a fraction of the base health
+    pub enemy_health_per_stage: f32,
+
+    // Gameplay tuning
+    pub enemy_shoot_chance: usize,
+    pub num_stars: usize,
+    pub player_invincibility_frames: u32,
+    pub player_contact_damage: f32,
+    pub wave_grace_period: usize,
+    //every this many stages, a boss replaces the regular wave
+    pub boss_every: usize,
+    pub player_max_health: f32,
+    pub player_min_health_restore: f32,
+    pub exp_to_level: f32,
+
+    // Bombs
+    pub player_bombs: u32,
+    pub bomb_radius: f32,
+    pub bomb_damage: f32,
+    pub bomb_invincibility_frames: u32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            friction: 0.1,
+            enemies_per_row: 7,
+            enemy_rows: 3,
+            wave_budget: 6.0,
+            wave_budget_per_stage: 3.0,
+            enemy_health_per_stage: 0.05,
+            enemy_shoot_chance: 10,
+            num_stars: 300,
+            player_invincibility_frames: 60,
+            player_contact_damage: 20.0,
+            wave_grace_period: 200,
+            boss_every: 5,
+            player_max_health: 100.0,
+            player_min_health_restore: 25.0,
+            exp_to_level: 100.0,
+            player_bombs: 3,
+            bomb_radius: 250.0,
+            bomb_damage: 60.0,
+            bomb_invincibility_frames: 90,
+        }
+    }
+}
+
+impl Config {
+    /// Parse a config, keeping the default of every key that is unknown or has an invalid
+    /// value. Returns the config along with why each rejected key was left out.
+    pub fn from_json(json: &str) -> Result<(Config, Vec<String>), String> {
+        let keys: Map<String, Value> = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        let mut accepted = Map::new();
+        let mut config = Config::default();
+        let mut rejected = Vec::new();
+        //add keys one at a time so a bad one only costs its own value
+        for (key, value) in keys {
+            accepted.insert(key.clone(), value);
+            let candidate = serde_json::from_value::<Config>(Value::Object(accepted.clone()))
+                .map_err(|e| e.to_string())
+                .and_then(|candidate| candidate.validate().map(|_| candidate));
+            match candidate {
+                Ok(candidate) => config = candidate,
+                Err(e) => {
+                    accepted.remove(&key);
+                    rejected.push(format!("{key}: {e}"));
+                }
+            }
+        }
+        Ok((config, rejected))
+    }
+
+    //how much tougher than their base health enemies of a generated stage are
+    pub fn enemy_health_scale(&self, stage: usize) -> f32 {
+        1.0 + self.enemy_health_per_stage * stage.saturating_sub(1) as f32
+    }
+
+    /// Load the config file, reporting every key that had to be ignored.
+    pub fn load_or_default(path: &Path) -> Config {
+        let result = fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|json| Config::from_json(&json));
+        match result {
+            Ok((config, rejected)) => {
+                for problem in rejected {
+                    eprintln!("error in {}: {problem}; ignoring it", path.display());
+                }
+                config
+            }
+            Err(e) => {
+                eprintln!("error in {}: {e}; using default config", path.display());
+                Config::default()
+            }
+        }
+    }
+
+    //friction outside 0..1, zero spawn and fire settings, negative budgets, and a player who
+    //starts dead or can never level up
+    fn validate(&self) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&self.friction) {
+            return Err(format!("friction must be between 0 and 1, got {}", self.friction));
+        }
+        if self.enemy_shoot_chance == 0 {
+            return Err("enemy_shoot_chance must be at least 1".to_string());
+        }
+        if self.boss_every == 0 {
+            return Err("boss_every must be at least 1".to_string());
+        }
+        if self.enemies_per_row == 0 || self.enemy_rows == 0 {
+            return Err("enemies_per_row and enemy_rows must be at least 1".to_string());
+        }
+        if self.wave_budget < 0.0 || self.wave_budget_per_stage < 0.0 || self.enemy_health_per_stage < 0.0 {
+            return Err("wave_budget, wave_budget_per_stage and enemy_health_per_stage must not be negative".to_string());
+        }
+        if self.player_max_health <= 0.0 || self.exp_to_level <= 0.0 {
+            return Err("player_max_health and exp_to_level must be positive".to_string());
+        }
+        Ok(())
+    }
+}
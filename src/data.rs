@@ -0,0 +1,91 @@
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::config::{Config, CONFIG_FILE};
+use crate::enemy::{EnemyDef, ENEMIES_FILE};
+use crate::hitbox::{HitboxLayout, SpriteHitboxes, HITBOXES_FILE};
+use crate::shooter::PLAYER_FRAME;
+use crate::spritesheet::{SpriteSheetData, SPRITESHEET_FILE};
+use crate::wave::{WaveScript, WAVES_FILE};
+use crate::weapon::{WeaponDef, WEAPONS_FILE};
+
+/// Everything read from the resources directory that defines how the game plays.
+/// Loaded once at startup and shared by live games and headless replays.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameData {
+    pub config: Config,
+    pub weapons: Vec<WeaponDef>,
+    pub enemies: Vec<EnemyDef>,
+    //authored stages, played in order before waves are generated
+    pub waves: Vec<WaveScript>,
+    pub hitboxes: SpriteHitboxes,
+}
+
+impl GameData {
+    pub fn load(resource_dir: &Path) -> Result<GameData, String> {
+        let weapons: Vec<WeaponDef> = load_json(&resource_dir.join(WEAPONS_FILE))?;
+        if weapons.is_empty() {
+            return Err(format!("{WEAPONS_FILE} must define at least one weapon"));
+        }
+        for weapon in &weapons {
+            weapon.validate().map_err(|e| format!("error in {WEAPONS_FILE}: {e}"))?;
+        }
+        let atlas: SpriteSheetData = load_json(&resource_dir.join(SPRITESHEET_FILE))?;
+        let layouts: HashMap<String, HitboxLayout> = load_json(&resource_dir.join(HITBOXES_FILE))?;
+        let hitboxes = SpriteHitboxes::new(layouts, &atlas).map_err(|e| format!("error in {HITBOXES_FILE}: {e}"))?;
+        if !hitboxes.contains(PLAYER_FRAME) {
+            return Err(format!("{HITBOXES_FILE} must declare a hitbox for the player frame {PLAYER_FRAME}"));
+        }
+
+        let enemies: Vec<EnemyDef> = load_json(&resource_dir.join(ENEMIES_FILE))?;
+        if !enemies.iter().any(|enemy| !enemy.boss && enemy.from_stage <= 1) {
+            return Err(format!("{ENEMIES_FILE} must define at least one non-boss enemy for stage 1"));
+        }
+        for enemy in &enemies {
+            enemy.validate().map_err(|e| format!("error in {ENEMIES_FILE}: {e}"))?;
+            if enemy.hitbox.is_none() {
+                if let Some(frame) = enemy.frames().find(|frame| !hitboxes.contains(frame)) {
+                    return Err(format!(
+                        "error in {ENEMIES_FILE}: {} has no hitbox, and {HITBOXES_FILE} declares none for {frame}",
+                        enemy.name,
+                    ));
+                }
+            }
+        }
+        let waves: Vec<WaveScript> = load_json(&resource_dir.join(WAVES_FILE))?;
+        for event in waves.iter().flat_map(|wave| &wave.events) {
+            if !enemies.iter().any(|enemy| enemy.name == event.enemy) {
+                return Err(format!("error in {WAVES_FILE}: unknown enemy {}", event.enemy));
+            }
+            if event.group.count == 0 {
+                return Err(format!("error in {WAVES_FILE}: a group of {} must have at least one enemy", event.enemy));
+            }
+            if !event.dive_delays.is_empty() && event.dive_delays.len() != event.group.count as usize {
+                return Err(format!("error in {WAVES_FILE}: a group of {} needs one dive delay per enemy", event.enemy));
+            }
+            if event.health <= 0.0 {
+                return Err(format!("error in {WAVES_FILE}: a group of {} must have positive health", event.enemy));
+            }
+            if let Some(movement) = &event.movement {
+                movement.validate().map_err(|e| format!("error in {WAVES_FILE}: {e}"))?;
+            }
+            if let Some(fire) = &event.fire {
+                fire.validate().map_err(|e| format!("error in {WAVES_FILE}: {e}"))?;
+            }
+        }
+        Ok(GameData {
+            config: Config::load_or_default(&resource_dir.join(CONFIG_FILE)),
+            weapons,
+            enemies,
+            waves,
+            hitboxes,
+        })
+    }
+}
+
+pub fn load_json<T: DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let json = fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    serde_json::from_str(&json).map_err(|e| format!("error in {}: {e}", path.display()))
+}
@@ -0,0 +1,138 @@
+use serde::Deserialize;
+
+use crate::bullet_pattern::BulletPattern;
+use crate::hitbox::HitboxLayout;
+use crate::movement::Pattern;
+use crate::shooter::{BulletSize, BulletType};
+use crate::spritesheet::SpriteAnimation;
+
+pub const ENEMIES_FILE: &str = "enemies.json";
+
+/// An enemy archetype as described in `resources/enemies.json`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EnemyDef {
+    pub name: String,
+    //first stage this enemy can appear in
+    #[serde(default = "first_stage")]
+    pub from_stage: usize,
+    pub health: f32,
+    pub size: f32,
+    //in fractions of `size`; without one, the hitboxes declared for its sprite frames are used
+    #[serde(default)]
+    pub hitbox: Option<HitboxLayout>,
+    //loops for as long as the enemy is alive
+    pub idle: SpriteAnimation,
+    //plays once whenever the enemy takes damage, if given
+    #[serde(default)]
+    pub hurt: Option<SpriteAnimation>,
+    //multiplied with the sprite's colors, to tell apart enemies sharing a sprite
+    #[serde(default = "white")]
+    pub tint: [f32; 4],
+    pub movement: Pattern,
+    //enemies without one never shoot
+    #[serde(default)]
+    pub fire: Option<EnemyFire>,
+    pub score: u32,
+    pub experience: f32,
+    //difficulty points a generated wave spends on each enemy of this type
+    #[serde(default = "one")]
+    pub cost: f32,
+
+    //bosses spawn alone every `boss_every` stages instead of a regular wave
+    #[serde(default)]
+    pub boss: bool,
+    //separately damageable pieces added under the root of the hitbox tree; hits anywhere else
+    //damage `health`, which still decides when the enemy dies
+    #[serde(default)]
+    pub parts: Vec<PartDef>,
+    //attack phases: `fire` is used until a part is destroyed, then each destroyed part
+    //moves on to the next entry
+    #[serde(default)]
+    pub phases: Vec<EnemyFire>,
+}
+
+/// A destructible piece of an enemy, such as a boss turret or wing.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PartDef {
+    pub name: String,
+    pub health: f32,
+    pub hitbox: HitboxLayout,
+    //awarded when the part is destroyed
+    #[serde(default)]
+    pub score: u32,
+}
+
+fn first_stage() -> usize { 1 }
+fn white() -> [f32; 4] { [1.0; 4] }
+fn one() -> f32 { 1.0 }
+
+/// How an enemy shoots at the player.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EnemyFire {
+    pub bullet_type: BulletType,
+    #[serde(default)]
+    pub bullet_size: BulletSize,
+    pub speed: f32,
+    pub damage: f32,
+    //fires on this pattern's schedule; without one, fires single aimed shots at random
+    #[serde(default)]
+    pub pattern: Option<BulletPattern>,
+    //how often random shots are fired, relative to the base `enemy_shoot_chance`
+    #[serde(default = "one")]
+    pub rate: f32,
+    //how far random shots stray from the player: 0 is perfect aim, 1 is the default scatter
+    #[serde(default = "one")]
+    pub spread: f32,
+}
+
+impl EnemyFire {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.rate <= 0.0 {
+            return Err("fire rate must be positive".to_string());
+        }
+        self.pattern.as_ref().map_or(Ok(()), BulletPattern::validate)
+    }
+}
+
+impl EnemyDef {
+    //every sprite frame this enemy can show
+    pub fn frames(&self) -> impl Iterator<Item = &String> {
+        self.idle.frames.iter().chain(self.hurt.iter().flat_map(|hurt| &hurt.frames))
+    }
+
+    //keys of this enemy's animations in the `SpriteAnimationRegistry`
+    pub fn idle_key(&self) -> String { format!("{}_idle", self.name) }
+    pub fn hurt_key(&self) -> String { format!("{}_hurt", self.name) }
+
+    //the same enemy with its hull and parts `factor` times as tough
+    pub fn with_health_scale(mut self, factor: f32) -> Self {
+        self.health *= factor;
+        for part in &mut self.parts {
+            part.health *= factor;
+        }
+        self
+    }
+
+    //an enemy that cannot be hit, drawn or bought, plus any bad hitbox, fire or movement it uses
+    pub fn validate(&self) -> Result<(), String> {
+        if self.health <= 0.0 || self.size <= 0.0 || self.cost <= 0.0 {
+            return Err(format!("{}: health, size and cost must be positive", self.name));
+        }
+        if self.idle.frames.is_empty() || self.hurt.as_ref().is_some_and(|hurt| hurt.frames.is_empty()) {
+            return Err(format!("{}: animations need at least one frame", self.name));
+        }
+        if self.parts.iter().any(|part| part.health <= 0.0) {
+            return Err(format!("{}: part health must be positive", self.name));
+        }
+        for layout in self.hitbox.iter().chain(self.parts.iter().map(|part| &part.hitbox)) {
+            layout.validate().map_err(|e| format!("{}: {e}", self.name))?;
+        }
+        for fire in self.fire.iter().chain(&self.phases) {
+            fire.validate().map_err(|e| format!("{}: {e}", self.name))?;
+        }
+        self.movement.validate().map_err(|e| format!("{}: {e}", self.name))
+    }
+}
@@ -1,52 +1,309 @@
-use std::collections::LinkedList;
+use std::collections::{HashMap, LinkedList};
 
 use ggez::glam::Vec2;
-use crate::config::{WORLD_WIDTH, WORLD_HEIGHT};
+use serde::Deserialize;
 
-//sub-divides screen into 10x10 grid for the purposes of speeding up collision detection
-const GRID_RESOLUTION: (f32, f32) = (10.0, 10.0);
+use crate::spritesheet::SpriteSheetData;
 
+pub const HITBOXES_FILE: &str = "hitboxes.json";
+
+/// Exact outline of a hitbox, in world coordinates.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    //fills the hitbox's bounding box
+    Rect,
+    Circle { center: Vec2, radius: f32 },
+    //every point within `radius` of the segment from `start` to `end`
+    Capsule { start: Vec2, end: Vec2, radius: f32 },
+    //a rectangle turned by `angle` radians around its center
+    OrientedRect { center: Vec2, half_size: Vec2, angle: f32 },
+    //convex, with its corners in order
+    Polygon { points: Vec<Vec2> },
+}
+
+/// A shape together with the axis-aligned box around it, which rejects most pairs cheaply
+/// before the exact shapes are compared.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Hitbox {
     pub point: Vec2,
-    pub size: Vec2
+    pub size: Vec2,
+    pub shape: Shape,
 }
 impl Hitbox {
     pub fn new(point: Vec2, size: Vec2) -> Hitbox {
         Hitbox {
             point,
-            size
+            size,
+            shape: Shape::Rect,
         }
     }
     pub fn new_square(point: Vec2, size: f32) -> Hitbox {
         Hitbox::new(point, Vec2::new(size, size))
     }
-    //move bounding box by a certain amount
+    pub fn circle(center: Vec2, radius: f32) -> Hitbox {
+        Hitbox::new(center - Vec2::splat(radius), Vec2::splat(2.0 * radius))
+            .with_shape(Shape::Circle { center, radius })
+    }
+    pub fn capsule(start: Vec2, end: Vec2, radius: f32) -> Hitbox {
+        Hitbox::new(start.min(end) - Vec2::splat(radius), (end - start).abs() + Vec2::splat(2.0 * radius))
+            .with_shape(Shape::Capsule { start, end, radius })
+    }
+    //the largest capsule that fits in the box at `point`, lying along its longer side
+    pub fn capsule_in(point: Vec2, size: Vec2) -> Hitbox {
+        let radius = size.min_element() / 2.0;
+        let center = point + size / 2.0;
+        let half_length = if size.x > size.y {
+            Vec2::new(size.x / 2.0 - radius, 0.0)
+        } else {
+            Vec2::new(0.0, size.y / 2.0 - radius)
+        };
+        Hitbox::capsule(center - half_length, center + half_length, radius)
+    }
+    pub fn oriented_rect(center: Vec2, half_size: Vec2, angle: f32) -> Hitbox {
+        Hitbox::around(&shape_corners(center, half_size, angle))
+            .with_shape(Shape::OrientedRect { center, half_size, angle })
+    }
+    //the convex hull of `points`
+    pub fn polygon(points: &[Vec2]) -> Hitbox {
+        let points = convex_hull(points);
+        Hitbox::around(&points).with_shape(Shape::Polygon { points })
+    }
+    fn with_shape(mut self, shape: Shape) -> Hitbox {
+        self.shape = shape;
+        self
+    }
+    //axis-aligned box around all of `points`
+    fn around(points: &[Vec2]) -> Hitbox {
+        let min = points.iter().fold(Vec2::splat(f32::INFINITY), |min, &p| min.min(p));
+        let max = points.iter().fold(Vec2::splat(f32::NEG_INFINITY), |max, &p| max.max(p));
+        Hitbox::new(min, max - min)
+    }
+    //move bounding box and shape by a certain amount
     pub fn move_delta(&mut self, delta: Vec2) {
         self.point += delta;
+        match &mut self.shape {
+            Shape::Rect => {}
+            Shape::Circle { center, .. } | Shape::OrientedRect { center, .. } => *center += delta,
+            Shape::Capsule { start, end, .. } => {
+                *start += delta;
+                *end += delta;
+            }
+            Shape::Polygon { points } => {
+                for point in points {
+                    *point += delta;
+                }
+            }
+        }
+    }
+    //corners of a polygonal shape, in order
+    pub fn outline(&self) -> Vec<Vec2> {
+        self.core().0
+    }
+    //every shape is a convex core (a point, a segment or a polygon) grown by a radius
+    fn core(&self) -> (Vec<Vec2>, f32) {
+        match &self.shape {
+            Shape::Rect => (shape_corners(self.point + self.size / 2.0, self.size / 2.0, 0.0), 0.0),
+            Shape::Circle { center, radius } => (vec![*center], *radius),
+            Shape::Capsule { start, end, radius } => (vec![*start, *end], *radius),
+            Shape::OrientedRect { center, half_size, angle } => (shape_corners(*center, *half_size, *angle), 0.0),
+            Shape::Polygon { points } => (points.clone(), 0.0),
+        }
     }
     //standard bounding box collision
-    pub fn collides_with(&self, other: &Hitbox) -> bool {
+    fn bounds_overlap(&self, other: &Hitbox) -> bool {
         self.point.x < other.point.x+other.size.x &&
         self.point.x+self.size.x > other.point.x &&
         self.point.y < other.point.y+other.size.y &&
         self.point.y+self.size.y > other.point.y
     }
+    //bounding boxes first, then the exact shapes if either is not just its box
+    pub fn collides_with(&self, other: &Hitbox) -> bool {
+        if !self.bounds_overlap(other) {
+            return false;
+        }
+        if self.shape == Shape::Rect && other.shape == Shape::Rect {
+            return true;
+        }
+        let ((a, a_radius), (b, b_radius)) = (self.core(), other.core());
+        cores_touch(&a, a_radius, &b, b_radius)
+    }
+    //box covering this one over a move by `delta` that ended where it is now
+    pub fn swept(&self, delta: Vec2) -> Hitbox {
+        let start = self.point - delta;
+        Hitbox::new(start.min(self.point), self.size + delta.abs())
+    }
+    //fraction t in [0, 1] of `delta` at which `other`, having just moved by `delta`, first touched this hitbox
+    pub fn sweep_intersection(&self, other: &Hitbox, delta: Vec2) -> Option<f32> {
+        //grow this box by the other one, so the other's corner can be traced as a point
+        let grown = Hitbox::new(self.point - other.size, self.size + other.size);
+        let t = grown.bounds_segment_intersection(other.point - delta, delta)?;
+        if self.shape == Shape::Rect && other.shape == Shape::Rect {
+            return Some(t);
+        }
+        let (core, radius) = other.core();
+        self.first_touch(&core, radius, delta, t)
+    }
+    //fraction t in [0, 1] along the segment `start -> end`, `width` wide, where it first touches this hitbox
+    pub fn segment_intersection(&self, start: Vec2, end: Vec2, width: f32) -> Option<f32> {
+        //trace the segment's center line against the box grown by half the width
+        let radius = width / 2.0;
+        let grown = Hitbox::new(self.point - Vec2::splat(radius), self.size + Vec2::splat(width));
+        let t = grown.bounds_segment_intersection(start, end - start)?;
+        if self.shape == Shape::Rect && radius == 0.0 {
+            return Some(t);
+        }
+        self.first_touch(&[end], radius, end - start, t)
+    }
+    //slab test: fraction t in [0, 1] along the segment `origin -> origin+delta` where it first enters the bounding box
+    fn bounds_segment_intersection(&self, origin: Vec2, delta: Vec2) -> Option<f32> {
+        let (mut t_enter, mut t_exit) = (0.0_f32, 1.0_f32);
+        for axis in 0..2 {
+            let (min, max) = (self.point[axis], self.point[axis] + self.size[axis]);
+            if delta[axis] == 0.0 {
+                //parallel to this slab, so it must already be between its sides
+                if origin[axis] < min || origin[axis] > max {
+                    return None;
+                }
+            } else {
+                let t1 = (min - origin[axis]) / delta[axis];
+                let t2 = (max - origin[axis]) / delta[axis];
+                t_enter = t_enter.max(t1.min(t2));
+                t_exit = t_exit.min(t1.max(t2));
+                if t_enter > t_exit {
+                    return None;
+                }
+            }
+        }
+        Some(t_enter)
+    }
+    //earliest t, no sooner than `t_min`, by which the core `moving` (at the end of a move by `delta`)
+    //grown by `radius` touched this hitbox on its way
+    fn first_touch(&self, moving: &[Vec2], radius: f32, delta: Vec2, t_min: f32) -> Option<f32> {
+        let (core, core_radius) = self.core();
+        //the ground covered up to t is the hull of the core's start and where it was at t
+        let touched_by = |t: f32| {
+            let swept: Vec<Vec2> = moving.iter()
+                .flat_map(|&p| [p - delta, p - delta + delta * t])
+                .collect();
+            cores_touch(&core, core_radius, &convex_hull(&swept), radius)
+        };
+        if !touched_by(1.0) {
+            return None;
+        }
+        if touched_by(t_min) {
+            return Some(t_min);
+        }
+        //the covered ground only grows with t, so bisect for where it starts touching
+        let (mut before, mut after) = (t_min, 1.0);
+        for _ in 0..CONTACT_ITERATIONS {
+            let t = (before + after) / 2.0;
+            if touched_by(t) { after = t } else { before = t }
+        }
+        Some(after)
+    }
+}
+
+//halvings of the search interval when looking for the moment two moving shapes touch
+const CONTACT_ITERATIONS: u32 = 12;
+
+//corners, in order, of a rectangle turned by `angle` around its center
+fn shape_corners(center: Vec2, half_size: Vec2, angle: f32) -> Vec<Vec2> {
+    let rotation = Vec2::from_angle(angle);
+    [Vec2::new(-1.0, -1.0), Vec2::new(1.0, -1.0), Vec2::new(1.0, 1.0), Vec2::new(-1.0, 1.0)]
+        .iter()
+        .map(|&corner| center + rotation.rotate(corner * half_size))
+        .collect()
+}
+
+//whether the convex cores `a` and `b`, grown by their radii, overlap
+fn cores_touch(a: &[Vec2], a_radius: f32, b: &[Vec2], b_radius: f32) -> bool {
+    //cores that overlap touch no matter the radii; otherwise the closest points are a corner of one
+    //and an edge of the other
+    if cores_overlap(a, b) {
+        return true;
+    }
+    let closest = |points: &[Vec2], edges: &[Vec2]| {
+        points.iter()
+            .flat_map(|&p| core_edges(edges).map(move |(start, end)| segment_distance(p, start, end)))
+            .fold(f32::INFINITY, f32::min)
+    };
+    closest(a, b).min(closest(b, a)) < a_radius + b_radius
+}
+
+fn cores_overlap(a: &[Vec2], b: &[Vec2]) -> bool {
+    let edges_cross = core_edges(a).any(|(a1, a2)| core_edges(b).any(|(b1, b2)| segments_cross(a1, a2, b1, b2)));
+    edges_cross || (a.len() > 2 && contains(a, b[0])) || (b.len() > 2 && contains(b, a[0]))
+}
+
+//edges of a core: a single point is its own edge, and a segment has just the one
+fn core_edges(points: &[Vec2]) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+    let count = if points.len() > 2 { points.len() } else { points.len().saturating_sub(1).max(1) };
+    (0..count).map(move |i| (points[i], points[(i + 1) % points.len()]))
+}
+
+//whether the convex polygon `polygon` contains `point`, for either winding order
+fn contains(polygon: &[Vec2], point: Vec2) -> bool {
+    let sides: Vec<f32> = core_edges(polygon).map(|(start, end)| (end - start).perp_dot(point - start)).collect();
+    sides.iter().all(|&side| side >= 0.0) || sides.iter().all(|&side| side <= 0.0)
+}
+
+fn segments_cross(a1: Vec2, a2: Vec2, b1: Vec2, b2: Vec2) -> bool {
+    let side = |p: Vec2, start: Vec2, end: Vec2| (end - start).perp_dot(p - start);
+    side(b1, a1, a2) * side(b2, a1, a2) < 0.0 && side(a1, b1, b2) * side(a2, b1, b2) < 0.0
+}
+
+fn segment_distance(point: Vec2, start: Vec2, end: Vec2) -> f32 {
+    let along = end - start;
+    let t = if along == Vec2::ZERO {
+        0.0
+    } else {
+        ((point - start).dot(along) / along.length_squared()).clamp(0.0, 1.0)
+    };
+    point.distance(start + along * t)
+}
+
+//monotone chain: corners of the smallest convex polygon around `points`, counter-clockwise.
+//Collinear points give just the two ends, and a single point itself
+fn convex_hull(points: &[Vec2]) -> Vec<Vec2> {
+    let mut sorted = points.to_vec();
+    sorted.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    sorted.dedup();
+    if sorted.len() < 3 {
+        return sorted;
+    }
+    let mut hull: Vec<Vec2> = Vec::with_capacity(sorted.len() + 1);
+    for pass in [sorted.clone(), sorted.into_iter().rev().collect()] {
+        let floor = hull.len();
+        for point in pass {
+            while let [.., before, last] = hull[floor..] {
+                if (last - before).perp_dot(point - before) > 0.0 {
+                    break;
+                }
+                hull.pop();
+            }
+            hull.push(point);
+        }
+        //each pass ends where the next one starts
+        hull.pop();
+    }
+    hull
 }
 
 /// A simple tree node: data + children
 #[derive(Debug, Clone, PartialEq)]
 pub struct HitboxNode {
     pub data: Hitbox,
-    pub children: Vec<HitboxNode>
+    pub children: Vec<HitboxNode>,
+    //identifies the part of an entity this subtree belongs to, e.g. a boss turret
+    pub tag: Option<usize>,
 }
 
 impl HitboxNode {
     pub fn new(data: Hitbox) -> HitboxNode {
-        HitboxNode { data, children: Vec::new() }
+        HitboxNode { data, children: Vec::new(), tag: None }
     }
-    pub fn with_child(mut self, child: HitboxNode) -> Self {
-        self.children.push(child);
+    pub fn with_tag(mut self, tag: usize) -> Self {
+        self.tag = Some(tag);
         self
     }
     pub fn is_leaf(&self) -> bool {
@@ -54,6 +311,116 @@ impl HitboxNode {
     }
 }
 
+/// Shape of a hitbox tree in local coordinates, built by scaling them into place: fractions of the
+/// entity's size in `enemies.json`, or sprite pixels in `hitboxes.json`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HitboxLayout {
+    //x, y, width, height
+    pub rect: [f32; 4],
+    #[serde(default)]
+    pub shape: LayoutShape,
+    #[serde(default)]
+    pub children: Vec<HitboxLayout>,
+}
+
+/// What part of its `rect` a hitbox layout node covers.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum LayoutShape {
+    //all of it
+    #[default]
+    Rect,
+    //the largest circle that fits inside
+    Circle,
+    //the largest capsule that fits inside, along its longer side
+    Capsule,
+    //a convex polygon with corners in the same coordinates as `rect`, which should enclose it
+    Polygon { points: Vec<[f32; 2]> },
+}
+
+impl HitboxLayout {
+    //local coordinates map to `position + local * scale`; a negative scale mirrors the layout
+    pub fn build(&self, position: Vec2, scale: Vec2) -> HitboxTree {
+        HitboxTree::new(self.build_node(position, scale))
+    }
+    pub fn build_node(&self, position: Vec2, scale: Vec2) -> HitboxNode {
+        let [x, y, w, h] = self.rect;
+        let (corner, opposite) = (position + Vec2::new(x, y) * scale, position + Vec2::new(x + w, y + h) * scale);
+        let (point, rect_size) = (corner.min(opposite), (opposite - corner).abs());
+        let data = match &self.shape {
+            LayoutShape::Rect => Hitbox::new(point, rect_size),
+            LayoutShape::Circle => Hitbox::circle(point + rect_size / 2.0, rect_size.min_element() / 2.0),
+            LayoutShape::Capsule => Hitbox::capsule_in(point, rect_size),
+            LayoutShape::Polygon { points } => {
+                let points: Vec<Vec2> = points.iter().map(|&p| position + Vec2::from(p) * scale).collect();
+                Hitbox::polygon(&points)
+            }
+        };
+        HitboxNode {
+            data,
+            children: self.children.iter().map(|child| child.build_node(position, scale)).collect(),
+            tag: None,
+        }
+    }
+    //polygons with fewer than three corners, anywhere in the tree
+    pub fn validate(&self) -> Result<(), String> {
+        if let LayoutShape::Polygon { points } = &self.shape {
+            if points.len() < 3 {
+                return Err("hitbox polygons need at least three corners".to_string());
+            }
+        }
+        self.children.iter().try_for_each(HitboxLayout::validate)
+    }
+    //center of the root rectangle, for an entity at `position` with the given `scale`
+    pub fn center(&self, position: Vec2, scale: Vec2) -> Vec2 {
+        let [x, y, w, h] = self.rect;
+        position + Vec2::new(x + w / 2.0, y + h / 2.0) * scale
+    }
+}
+
+/// Hitbox layouts declared per sprite frame in `resources/hitboxes.json`, in pixels of the frame's
+/// untrimmed source image, so artists can fit them to a ship without touching the code.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SpriteHitboxes {
+    //each frame's layout, and the width of its untrimmed source image
+    frames: HashMap<String, (HitboxLayout, f32)>,
+}
+
+impl SpriteHitboxes {
+    pub fn new(layouts: HashMap<String, HitboxLayout>, atlas: &SpriteSheetData) -> Result<SpriteHitboxes, String> {
+        let mut frames = HashMap::new();
+        for (frame, layout) in layouts {
+            let sprite = atlas.frames.get(&frame).ok_or_else(|| format!("unknown sprite frame {frame}"))?;
+            layout.validate().map_err(|e| format!("{frame}: {e}"))?;
+            frames.insert(frame, (layout, sprite.source_size.w as f32));
+        }
+        Ok(SpriteHitboxes { frames })
+    }
+    pub fn contains(&self, frame: &str) -> bool {
+        self.frames.contains_key(frame)
+    }
+    /// The tree declared for `frame`, for an entity whose untrimmed source image is drawn from `position`
+    /// at `scale` times its pixel size. `mirrored` flips the tree across the source width, the same
+    /// way a mirrored sprite is drawn.
+    pub fn build(&self, frame: &str, position: Vec2, scale: f32, mirrored: bool) -> Option<HitboxTree> {
+        let (layout, source_width) = self.frames.get(frame)?;
+        Some(if mirrored {
+            layout.build(position + Vec2::new(source_width * scale, 0.0), Vec2::new(-scale, scale))
+        } else {
+            layout.build(position, Vec2::splat(scale))
+        })
+    }
+}
+
+/// Where two hitbox trees touched: the tags of the colliding leaves,
+/// each inherited from the nearest tagged ancestor if the leaf itself has none.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TreeHit {
+    pub own_tag: Option<usize>,
+    pub other_tag: Option<usize>,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct HitboxTree {
     root: HitboxNode
@@ -62,40 +429,51 @@ impl HitboxTree {
     pub fn new(root: HitboxNode) -> HitboxTree {
         HitboxTree { root }
     }
-    pub fn collides_with(&self, other: &HitboxTree) -> bool {
+    //the root box, which covers the whole tree
+    pub fn bounds(&self) -> &Hitbox {
+        &self.root.data
+    }
+    //attach `node` under the root, whose box should already cover it
+    pub fn add_subtree(&mut self, node: HitboxNode) {
+        self.root.children.push(node);
+    }
+    //drop every subtree tagged `tag`, e.g. once that part has been destroyed
+    pub fn remove_tagged(&mut self, tag: usize) {
+        Self::remove_tagged_recursive(&mut self.root, tag);
+    }
+    fn remove_tagged_recursive(node: &mut HitboxNode, tag: usize) {
+        node.children.retain(|child| child.tag != Some(tag));
+        for child in &mut node.children {
+            Self::remove_tagged_recursive(child, tag);
+        }
+    }
+    pub fn collides_with(&self, other: &HitboxTree) -> Option<TreeHit> {
         //we are using LinkedList as a light-weight queue (need to add to end and pop from beginning)
-        let mut self_queue: LinkedList<&HitboxNode> = LinkedList::new();
-        let mut other_queue: LinkedList<&HitboxNode> = LinkedList::new();
+        //each entry carries the tag its node inherits
+        let mut self_queue: LinkedList<(&HitboxNode, Option<usize>)> = LinkedList::new();
+        let mut other_queue: LinkedList<(&HitboxNode, Option<usize>)> = LinkedList::new();
         //seed with root nodes
         let (self_root, other_root) = (&self.root, &other.root);
-        self_queue.push_back(self_root);
-        other_queue.push_back(other_root);
-        //preliminary collision
-        //get location on 10x10 grid and check to see if objects are in the same grid tile
-        let self_grid = get_grid_square(self_root.data.point);
-        let other_grid = get_grid_square(other_root.data.point);
-        if (self_grid.0-other_grid.0).abs() > 1.0 ||
-            (self_grid.1-other_grid.1).abs() > 1.0 {
-            return false;
-        }
+        self_queue.push_back((self_root, self_root.tag));
+        other_queue.push_back((other_root, other_root.tag));
         //hierarchical collision
         //start at root node, and attempt to find a collision between leaf nodes
-        while let (Some(self_node), Some(other_node))=(self_queue.front(), other_queue.front()) {
+        while let (Some(&(self_node, self_tag)), Some(&(other_node, other_tag))) = (self_queue.front(), other_queue.front()) {
             //collision found! (using bounding box algorithm on current Hitbox node)
             if self_node.data.collides_with(&other_node.data) {
                 //if both are leaf nodes, then we've found the most specific collision we can
                 if self_node.is_leaf() && other_node.is_leaf() {
-                    return true;
+                    return Some(TreeHit { own_tag: self_tag, other_tag });
                 } else if !other_node.children.is_empty() {
                     //if the other node has children, explore those next
                     for child in &other_node.children {
-                        other_queue.push_back(child);
+                        other_queue.push_back((child, child.tag.or(other_tag)));
                     }
                     other_queue.pop_front();
                 } else if !self_node.children.is_empty() {
                     //otherwise, explore this node's children if it has any
                     for child in &self_node.children {
-                        self_queue.push_back(child);
+                        self_queue.push_back((child, child.tag.or(self_tag)));
                     }
                     self_queue.pop_front();
                 }
@@ -109,7 +487,47 @@ impl HitboxTree {
                 }
             }
         }
-        false
+        None
+    }
+    /// Earliest contact between the leaves of both trees while `other` moved by `delta` to where it
+    /// is now, as a fraction t in [0, 1] of `delta`, so fast objects cannot pass through thin boxes.
+    pub fn sweep_hit(&self, other: &HitboxTree, delta: Vec2) -> Option<(f32, TreeHit)> {
+        Self::sweep_hit_recursive(&self.root, self.root.tag, &other.root, other.root.tag, delta)
+    }
+    fn sweep_hit_recursive(
+        node: &HitboxNode, tag: Option<usize>, other: &HitboxNode, other_tag: Option<usize>, delta: Vec2,
+    ) -> Option<(f32, TreeHit)> {
+        //children are only explored when their parents touched at some point during the move
+        let t = node.data.sweep_intersection(&other.data, delta)?;
+        let hits: Vec<(f32, TreeHit)> = if !other.is_leaf() {
+            other.children.iter()
+                .filter_map(|child| Self::sweep_hit_recursive(node, tag, child, child.tag.or(other_tag), delta))
+                .collect()
+        } else if !node.is_leaf() {
+            node.children.iter()
+                .filter_map(|child| Self::sweep_hit_recursive(child, child.tag.or(tag), other, other_tag, delta))
+                .collect()
+        } else {
+            return Some((t, TreeHit { own_tag: tag, other_tag }));
+        };
+        hits.into_iter().min_by(|a, b| a.0.total_cmp(&b.0))
+    }
+    /// Nearest leaf touched by the segment `start -> end`, `width` wide, as a fraction t in [0, 1] of
+    /// the way along it, along with the tag that leaf inherits.
+    pub fn segment_hit(&self, start: Vec2, end: Vec2, width: f32) -> Option<(f32, Option<usize>)> {
+        Self::segment_hit_recursive(&self.root, self.root.tag, start, end, width)
+    }
+    fn segment_hit_recursive(
+        node: &HitboxNode, tag: Option<usize>, start: Vec2, end: Vec2, width: f32,
+    ) -> Option<(f32, Option<usize>)> {
+        //children are only explored when the segment passes through their parent
+        let t = node.data.segment_intersection(start, end, width)?;
+        if node.is_leaf() {
+            return Some((t, tag));
+        }
+        node.children.iter()
+            .filter_map(|child| Self::segment_hit_recursive(child, child.tag.or(tag), start, end, width))
+            .min_by(|a, b| a.0.total_cmp(&b.0))
     }
     //helper to move all bounding boxes in this tree by a certain amount
     pub fn move_delta(&mut self, delta: Vec2) {
@@ -135,8 +553,167 @@ impl HitboxTree {
     }
 }
 
-//convert a Vec2 to a discrete location on a grid
-//returns (f32, f32) as a convenience, but all components are floored
-fn get_grid_square(p: Vec2) -> (f32, f32) {
-    ((p.x * GRID_RESOLUTION.0 / WORLD_WIDTH).floor(), (p.y * GRID_RESOLUTION.1 / WORLD_HEIGHT).floor())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f32, b: f32, tolerance: f32) {
+        assert!((a - b).abs() <= tolerance, "expected {b}, got {a}");
+    }
+
+    //a target 2px thick with a tagged leaf, and a 4px bullet that crossed it in one 50px move
+    fn thin_target() -> HitboxTree {
+        let mut tree = HitboxTree::new(HitboxNode::new(Hitbox::new(Vec2::new(0.0, 80.0), Vec2::new(40.0, 30.0))));
+        tree.add_subtree(HitboxNode::new(Hitbox::new(Vec2::new(0.0, 100.0), Vec2::new(40.0, 2.0))).with_tag(3));
+        tree.add_subtree(HitboxNode::new(Hitbox::new(Vec2::new(0.0, 90.0), Vec2::new(40.0, 2.0))).with_tag(4));
+        tree
+    }
+
+    fn bullet_at(point: Vec2) -> HitboxTree {
+        HitboxTree::new(HitboxNode::new(Hitbox::new_square(point, 4.0)))
+    }
+
+    #[test]
+    fn fast_bullet_hits_thin_target_it_passed_through() {
+        let target = thin_target();
+        let delta = Vec2::new(0.0, -50.0);
+        let bullet = bullet_at(Vec2::new(18.0, 80.0));
+        //neither where the bullet started nor where it ended overlaps the first leaf
+        let leaf = &target.root.children[0].data;
+        assert!(!leaf.collides_with(bullet.bounds()));
+        assert!(!leaf.collides_with(&Hitbox::new_square(Vec2::new(18.0, 130.0), 4.0)));
+
+        let (t, hit) = target.sweep_hit(&bullet, delta).expect("the sweep should catch the bullet");
+        //moving up, its top edge (starting at 130) reached the leaf's bottom (102) after 28 of its 50px
+        assert_close(t, 28.0 / 50.0, 1e-4);
+        assert_eq!(hit, TreeHit { own_tag: Some(3), other_tag: None });
+    }
+
+    #[test]
+    fn bullet_passing_beside_thin_target_misses() {
+        let target = thin_target();
+        let bullet = bullet_at(Vec2::new(45.0, 80.0));
+        assert!(target.sweep_hit(&bullet, Vec2::new(0.0, -50.0)).is_none());
+    }
+
+    #[test]
+    fn wide_segment_hits_what_its_center_line_misses() {
+        let target = thin_target();
+        let (start, end) = (Vec2::new(44.0, 200.0), Vec2::new(44.0, 0.0));
+        assert!(target.segment_hit(start, end, 0.0).is_none());
+        let (t, tag) = target.segment_hit(start, end, 9.0).expect("a 9px segment reaches 4.5px to the side");
+        //its rounded front, 4px to the side of the tagged leaf's corner at (40, 102), reaches it
+        //a little before its center line passes the leaf's near side
+        let contact_y = 102.0 + (4.5_f32.powi(2) - 4.0_f32.powi(2)).sqrt();
+        assert_close(t, (200.0 - contact_y) / 200.0, 1.0 / 4096.0);
+        assert_eq!(tag, Some(3));
+        assert!(target.segment_hit(Vec2::new(45.0, 200.0), Vec2::new(45.0, 0.0), 9.0).is_none());
+    }
+
+    //one of every shape around `center`, each covering at least the disk of radius 7 around it
+    //and reaching no more than 10 from it
+    fn shapes_at(center: Vec2) -> Vec<Hitbox> {
+        let hexagon: Vec<Vec2> = (0..6)
+            .map(|i| center + Vec2::from_angle(i as f32 * std::f32::consts::TAU / 6.0) * 9.0)
+            .collect();
+        vec![
+            Hitbox::new_square(center - Vec2::splat(7.0), 14.0),
+            Hitbox::circle(center, 8.0),
+            Hitbox::capsule(center - Vec2::new(3.0, 0.0), center + Vec2::new(3.0, 0.0), 7.0),
+            Hitbox::oriented_rect(center, Vec2::splat(7.0), 0.4),
+            Hitbox::polygon(&hexagon),
+        ]
+    }
+
+    #[test]
+    fn every_shape_pair_hits_when_close_and_misses_when_apart() {
+        let a = Vec2::new(100.0, 100.0);
+        for direction in [Vec2::X, Vec2::NEG_Y, Vec2::new(1.0, 1.0).normalize(), Vec2::new(-3.0, 1.0).normalize()] {
+            for first in shapes_at(a) {
+                for (near, far) in shapes_at(a + direction * 13.0).into_iter().zip(shapes_at(a + direction * 21.0)) {
+                    assert!(first.collides_with(&near), "{first:?} should hit {near:?}");
+                    assert!(near.collides_with(&first), "{near:?} should hit {first:?}");
+                    assert!(!first.collides_with(&far), "{first:?} should miss {far:?}");
+                    assert!(!far.collides_with(&first), "{far:?} should miss {first:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn shapes_that_only_touch_do_not_collide() {
+        let square = Hitbox::new_square(Vec2::ZERO, 10.0);
+        assert!(!square.collides_with(&Hitbox::new_square(Vec2::new(10.0, 0.0), 10.0)));
+        assert!(!Hitbox::circle(Vec2::ZERO, 5.0).collides_with(&Hitbox::circle(Vec2::new(10.0, 0.0), 5.0)));
+        assert!(Hitbox::circle(Vec2::ZERO, 5.0).collides_with(&Hitbox::circle(Vec2::new(9.9, 0.0), 5.0)));
+        //overlapping the square's corner, and the diamond's bounding box but not the diamond
+        let circle = Hitbox::circle(Vec2::new(-2.0, -2.0), 4.0);
+        let diamond = Hitbox::oriented_rect(Vec2::splat(5.0), Vec2::splat(5.0), std::f32::consts::FRAC_PI_4);
+        assert!(!circle.collides_with(&diamond));
+        assert!(circle.collides_with(&square));
+    }
+
+    #[test]
+    fn capsule_hits_along_its_side_but_not_past_its_cap() {
+        let capsule = Hitbox::capsule(Vec2::new(0.0, 0.0), Vec2::new(40.0, 0.0), 3.0);
+        assert!(capsule.collides_with(&Hitbox::circle(Vec2::new(20.0, 5.0), 2.5)));
+        assert!(!capsule.collides_with(&Hitbox::circle(Vec2::new(20.0, 6.0), 2.5)));
+        //inside the bounding box corner, outside the rounded cap
+        assert!(!capsule.collides_with(&Hitbox::new_square(Vec2::new(-3.0, -3.0), 0.5)));
+        assert!(capsule.collides_with(&Hitbox::new_square(Vec2::new(-1.0, -1.0), 0.5)));
+    }
+
+    #[test]
+    fn rect_sweep_enters_at_the_slab_boundary() {
+        let wall = Hitbox::new(Vec2::new(50.0, 0.0), Vec2::new(2.0, 100.0));
+        //a 4px box that moved from x 0 to x 100 along y 40
+        let mover = Hitbox::new_square(Vec2::new(100.0, 40.0), 4.0);
+        assert_close(wall.sweep_intersection(&mover, Vec2::new(100.0, 0.0)).unwrap(), 0.46, 1e-6);
+        //moving parallel to the wall, alongside it
+        let beside = Hitbox::new_square(Vec2::new(60.0, 90.0), 4.0);
+        assert!(wall.sweep_intersection(&beside, Vec2::new(0.0, 80.0)).is_none());
+        //already overlapping at the start of the move
+        let inside = Hitbox::new_square(Vec2::new(49.0, 60.0), 4.0);
+        assert_eq!(wall.sweep_intersection(&inside, Vec2::new(0.0, 20.0)), Some(0.0));
+    }
+
+    #[test]
+    fn curved_sweep_bisects_to_the_moment_of_contact() {
+        let diamond = Hitbox::oriented_rect(Vec2::ZERO, Vec2::splat(10.0), std::f32::consts::FRAC_PI_4);
+        //a circle rising from (5, 60) to (5, -40) first meets the diamond's lower right edge,
+        //x + y = 10 * sqrt(2), once its center is 2 from it
+        let circle = Hitbox::circle(Vec2::new(5.0, -40.0), 2.0);
+        let t = diamond.sweep_intersection(&circle, Vec2::new(0.0, -100.0)).unwrap();
+        let contact_y = 10.0 * 2.0_f32.sqrt() - 5.0 + 2.0 * 2.0_f32.sqrt();
+        //12 halvings narrow the search down to a 4096th of the move
+        assert_close(t, (60.0 - contact_y) / 100.0, 1.0 / 4096.0);
+        //a circle gliding along that edge 2.5 out, through the diamond's bounding box
+        let offset = 10.0 * 2.0_f32.sqrt() + 2.5 * 2.0_f32.sqrt();
+        let gliding = Hitbox::circle(Vec2::new(-10.0, offset + 10.0), 2.0);
+        assert!(diamond.sweep_intersection(&gliding, Vec2::new(-30.0, 30.0)).is_none());
+        let closer = Hitbox::circle(Vec2::new(-10.0, offset + 9.0), 2.0);
+        assert!(diamond.sweep_intersection(&closer, Vec2::new(-30.0, 30.0)).is_some());
+    }
+
+    #[test]
+    fn hull_drops_collinear_and_duplicate_points() {
+        let corners = [Vec2::new(0.0, 0.0), Vec2::new(10.0, 0.0), Vec2::new(10.0, 10.0), Vec2::new(0.0, 10.0)];
+        let mut points = corners.to_vec();
+        //duplicates, an inside point and points along the edges
+        points.extend([corners[0], corners[2], Vec2::new(5.0, 5.0), Vec2::new(5.0, 0.0), Vec2::new(10.0, 5.0)]);
+        let hull = convex_hull(&points);
+        assert_eq!(hull.len(), 4);
+        for corner in corners {
+            assert!(hull.contains(&corner), "{corner} missing from {hull:?}");
+        }
+        //every turn goes the same way
+        for (i, &point) in hull.iter().enumerate() {
+            let (next, after) = (hull[(i + 1) % 4], hull[(i + 2) % 4]);
+            assert!((next - point).perp_dot(after - point) > 0.0);
+        }
+
+        let line = [Vec2::new(0.0, 0.0), Vec2::new(2.0, 2.0), Vec2::new(1.0, 1.0), Vec2::new(3.0, 3.0), Vec2::new(2.0, 2.0)];
+        assert_eq!(convex_hull(&line), vec![Vec2::new(0.0, 0.0), Vec2::new(3.0, 3.0)]);
+        assert_eq!(convex_hull(&[Vec2::ONE, Vec2::ONE, Vec2::ONE]), vec![Vec2::ONE]);
+    }
 }
@@ -0,0 +1,14 @@
+pub mod broadphase;
+pub mod bullet_pattern;
+pub mod config;
+pub mod data;
+pub mod enemy;
+pub mod hitbox;
+pub mod movement;
+pub mod replay;
+pub mod shooter;
+pub mod simulation;
+pub mod spritesheet;
+pub mod state;
+pub mod wave;
+pub mod weapon;
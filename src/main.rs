@@ -1,18 +1,48 @@
-mod config;
-mod hitbox;
-mod shooter;
-mod spritesheet;
-mod state;
-mod weapon;
-
 use std::env;
 use std::path;
 use ggez::*;
 
-use crate::config::{DISPLAY_WIDTH, DISPLAY_HEIGHT};
-use crate::state::State;
+use rust_shooter::config::{DISPLAY_WIDTH, DISPLAY_HEIGHT};
+use rust_shooter::data::GameData;
+use rust_shooter::replay::{Replay, verify_replays};
+use rust_shooter::state::State;
+
+const USAGE: &str = "usage: rust-shooter [--seed <n>] [--record <file>] [--replay <file>] [--verify-replays <dir> [--bless]]";
+
+#[derive(Debug, Default)]
+struct Options {
+    seed: Option<u64>,
+    record: Option<path::PathBuf>,
+    replay: Option<path::PathBuf>,
+    verify_replays: Option<path::PathBuf>,
+    bless: bool,
+}
+
+fn parse_options() -> GameResult<Options> {
+    let mut options = Options::default();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| GameError::CustomError(format!("{arg} expects a value\n{USAGE}")));
+        match arg.as_str() {
+            "--seed" => {
+                let seed = value()?;
+                options.seed = Some(seed.parse().map_err(|_| {
+                    GameError::CustomError(format!("--seed expects an unsigned integer, got {seed}"))
+                })?);
+            }
+            "--record" => options.record = Some(value()?.into()),
+            "--replay" => options.replay = Some(value()?.into()),
+            "--verify-replays" => options.verify_replays = Some(value()?.into()),
+            "--bless" => options.bless = true,
+            _ => return Err(GameError::CustomError(format!("unknown argument {arg}\n{USAGE}"))),
+        }
+    }
+    Ok(options)
+}
 
 fn main() -> GameResult {
+    let options = parse_options()?;
+
     let resource_dir = if let Ok(manifest_dir) = env::var("CARGO_MANIFEST_DIR") {
         let mut path = path::PathBuf::from(manifest_dir);
         path.push("resources");
@@ -21,11 +51,30 @@ fn main() -> GameResult {
         path::PathBuf::from("./resources")
     };
 
+    let data = GameData::load(&resource_dir).map_err(GameError::CustomError)?;
+
+    //headless regression check of recorded replays; never opens a window
+    if let Some(dir) = &options.verify_replays {
+        let failures = verify_replays(dir, &data, options.bless)?;
+        return if failures == 0 {
+            Ok(())
+        } else {
+            Err(GameError::CustomError(format!("{failures} replay(s) diverged")))
+        };
+    }
+
+    let playback = options.replay.as_deref().map(Replay::load).transpose()?;
+    let seed = match &playback {
+        Some(replay) => replay.seed,
+        None => options.seed.unwrap_or_else(rand::random),
+    };
+    println!("seed: {seed}");
+
     let cb = ContextBuilder::new("shooter_demo", "shishir")
         .window_setup(conf::WindowSetup::default().title("shooter_demo"))
         .window_mode(conf::WindowMode::default().dimensions(DISPLAY_WIDTH, DISPLAY_HEIGHT))
         .add_resource_path(resource_dir);
     let (mut ctx, event_loop) = cb.build()?;
-    let game = State::new(&mut ctx)?;
+    let game = State::new(&mut ctx, seed, &data, playback, options.record)?;
     event::run(ctx, event_loop, game)
 }
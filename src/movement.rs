@@ -0,0 +1,234 @@
+use ggez::glam::Vec2;
+use serde::Deserialize;
+use std::f32::consts::TAU;
+
+// =============================================================================
+// Splines
+// =============================================================================
+
+/// Catmull-Rom spline that passes through every one of its points.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spline {
+    points: Vec<Vec2>,
+}
+
+impl Spline {
+    pub fn new(points: Vec<Vec2>) -> Self {
+        assert!(!points.is_empty(), "a spline needs at least one point");
+        Self { points }
+    }
+
+    //`t` runs from 0 at the first point to 1 at the last, in equal steps per segment
+    pub fn sample(&self, t: f32) -> Vec2 {
+        let segments = self.points.len() - 1;
+        if segments == 0 {
+            return self.points[0];
+        }
+        let t = t.clamp(0.0, 1.0) * segments as f32;
+        let i = (t as usize).min(segments - 1);
+        let u = t - i as f32;
+
+        //the end points are repeated to give the first and last segments a tangent
+        let p = |j: isize| self.points[j.clamp(0, segments as isize) as usize];
+        let i = i as isize;
+        let (p0, p1, p2, p3) = (p(i - 1), p(i), p(i + 1), p(i + 2));
+
+        0.5 * (2.0 * p1
+            + (p2 - p0) * u
+            + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * u * u
+            + (3.0 * p1 - p0 - 3.0 * p2 + p3) * u * u * u)
+    }
+}
+
+// =============================================================================
+// Patterns
+// =============================================================================
+
+/// How an enemy moves relative to its slot in the formation.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Pattern {
+    //sits in its slot
+    Formation,
+    //sweeps side to side around its slot
+    Sine { amplitude: f32, period: u32 },
+    //every `interval` ticks, dives at the player and loops back up into its slot
+    Dive { interval: u32, dive_ticks: u32, return_ticks: u32 },
+    //follows keyframes given as offsets from its slot, one every `ticks_per_point` ticks
+    Path { points: Vec<[f32; 2]>, ticks_per_point: u32, looping: bool },
+}
+
+impl Pattern {
+    //zero-length periods and dives, which would stall the pattern, and paths without points
+    pub fn validate(&self) -> Result<(), String> {
+        let valid = match self {
+            Pattern::Formation => true,
+            Pattern::Sine { period, .. } => *period > 0,
+            Pattern::Dive { interval, dive_ticks, return_ticks } => {
+                *interval > 0 && *dive_ticks > 0 && *return_ticks > 0
+            }
+            Pattern::Path { points, ticks_per_point, .. } => !points.is_empty() && *ticks_per_point > 0,
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(format!("movement {self:?} needs non-zero periods and durations and at least one point"))
+        }
+    }
+}
+
+/// Motion shared by a whole formation: a slow sway plus a steady descent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Drift {
+    pub sway: f32,
+    pub period: u32,
+    pub descent: f32,
+}
+
+impl Default for Drift {
+    fn default() -> Self {
+        Self { sway: 30.0, period: 600, descent: 0.03 }
+    }
+}
+
+impl Drift {
+    fn offset(&self, age: u32) -> Vec2 {
+        Vec2::new(
+            self.sway * (TAU * age as f32 / self.period as f32).sin(),
+            self.descent * age as f32,
+        )
+    }
+}
+
+// =============================================================================
+// Movement
+// =============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+enum Phase {
+    InFormation,
+    //flying along `path` for `ticks` ticks, starting at age `start`; with `then_return`,
+    //it then takes that many ticks to fly back to its slot
+    Flying { path: Spline, start: u32, ticks: u32, then_return: Option<u32> },
+}
+
+/// Runs a `Pattern` for one enemy, producing its position every tick.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Movement {
+    pattern: Pattern,
+    //where this enemy's formation slot was at spawn
+    slot: Vec2,
+    drift: Drift,
+    //ticks since spawn
+    age: u32,
+    //staggers dives so a formation doesn't attack all at once
+    delay: u32,
+    phase: Phase,
+}
+
+impl Movement {
+    pub fn new(pattern: Pattern, slot: Vec2, drift: Drift) -> Self {
+        Self { pattern, slot, drift, age: 0, delay: 0, phase: Phase::InFormation }
+    }
+
+    pub fn with_delay(mut self, delay: u32) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    //fly in from `from` to the slot along a curve before joining the formation
+    pub fn with_entry(mut self, from: Vec2, ticks: u32) -> Self {
+        let to = self.home(ticks);
+        let swing = Vec2::new((to.x - from.x) * 0.5, (to.y - from.y) * 0.25);
+        self.phase = Phase::Flying {
+            path: Spline::new(vec![from, from + swing, to]),
+            start: 0,
+            ticks,
+            then_return: None,
+        };
+        self
+    }
+
+    pub fn age(&self) -> u32 {
+        self.age
+    }
+
+    pub fn position(&self) -> Vec2 {
+        match &self.phase {
+            Phase::InFormation => self.home(self.age),
+            Phase::Flying { path, start, ticks, .. } => {
+                path.sample((self.age - start) as f32 / *ticks as f32)
+            }
+        }
+    }
+
+    /// Advance one tick and return the new position. `target` is where a dive would aim,
+    /// or `None` if the enemy shouldn't start one.
+    pub fn step(&mut self, target: Option<Vec2>) -> Vec2 {
+        self.age += 1;
+
+        if let Phase::Flying { path, start, ticks, then_return } = &self.phase {
+            if self.age - start >= *ticks {
+                self.phase = match *then_return {
+                    Some(return_ticks) => self.return_path(path.sample(1.0), return_ticks),
+                    None => Phase::InFormation,
+                };
+            }
+        }
+
+        if let (Phase::InFormation, Some(target)) = (&self.phase, target) {
+            if let Pattern::Dive { interval, dive_ticks, return_ticks } = self.pattern {
+                if (self.age + self.delay).is_multiple_of(interval) {
+                    self.phase = self.dive_path(target, dive_ticks, return_ticks);
+                }
+            }
+        }
+
+        self.position()
+    }
+
+    //the slot plus the pattern's own motion around it
+    fn home(&self, age: u32) -> Vec2 {
+        let slot = self.slot + self.drift.offset(age);
+        match &self.pattern {
+            Pattern::Formation | Pattern::Dive { .. } => slot,
+            Pattern::Sine { amplitude, period } => {
+                slot + Vec2::new(amplitude * (TAU * age as f32 / *period as f32).sin(), 0.0)
+            }
+            Pattern::Path { points, ticks_per_point, looping } => {
+                let total = (points.len().max(2) - 1) as u32 * ticks_per_point;
+                let t = if *looping { age % total } else { age.min(total) };
+                let offsets = points.iter().map(|&[x, y]| Vec2::new(x, y)).collect();
+                slot + Spline::new(offsets).sample(t as f32 / total as f32)
+            }
+        }
+    }
+
+    //loop out to the side, pass through the target, and pull up past it
+    fn dive_path(&self, target: Vec2, ticks: u32, return_ticks: u32) -> Phase {
+        let start = self.position();
+        let side = if target.x < start.x { 1.0 } else { -1.0 };
+        Phase::Flying {
+            path: Spline::new(vec![
+                start,
+                start + Vec2::new(side * 60.0, -40.0),
+                target,
+                target + Vec2::new(-side * 100.0, -60.0),
+            ]),
+            start: self.age,
+            ticks,
+            then_return: Some(return_ticks),
+        }
+    }
+
+    //curve back to where the slot will be on arrival
+    fn return_path(&self, from: Vec2, ticks: u32) -> Phase {
+        let to = self.home(self.age + ticks);
+        Phase::Flying {
+            path: Spline::new(vec![from, (from + to) / 2.0 + Vec2::new(0.0, -80.0), to]),
+            start: self.age,
+            ticks,
+            then_return: None,
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use serde::{Serialize, Deserialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::data::GameData;
+use crate::simulation::{Input, Simulation};
+
+/// Final result of a run, stored alongside a replay so fixtures can detect regressions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayResult {
+    pub score: u32,
+    pub stage: usize,
+}
+
+impl ReplayResult {
+    pub fn of(sim: &Simulation) -> ReplayResult {
+        ReplayResult { score: sim.score, stage: sim.stage }
+    }
+}
+
+/// A seed plus the input snapshot of every tick, which together reproduce a run exactly.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Replay {
+    pub seed: u64,
+    //run-length encoded (tick count, input bits) pairs
+    inputs: Vec<(u32, u8)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected: Option<ReplayResult>,
+}
+
+impl Replay {
+    pub fn new(seed: u64) -> Replay {
+        Replay { seed, inputs: Vec::new(), expected: None }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Replay> {
+        let contents = fs::read_to_string(path)?;
+        serde_json::from_str(&contents).map_err(io::Error::from)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, serde_json::to_string(self)?)
+    }
+
+    pub fn record(&mut self, input: Input) {
+        let bits = input.to_bits();
+        match self.inputs.last_mut() {
+            Some((count, last)) if *last == bits => *count += 1,
+            _ => self.inputs.push((1, bits)),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.inputs.iter().map(|&(count, _)| count as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inputs.is_empty()
+    }
+
+    pub fn input_at(&self, tick: usize) -> Option<Input> {
+        let mut remaining = tick;
+        for &(count, bits) in &self.inputs {
+            if remaining < count as usize {
+                return Some(Input::from_bits(bits));
+            }
+            remaining -= count as usize;
+        }
+        None
+    }
+
+    fn iter(&self) -> impl Iterator<Item = Input> + '_ {
+        self.inputs.iter()
+            .flat_map(|&(count, bits)| std::iter::repeat_n(Input::from_bits(bits), count as usize))
+    }
+
+    /// Play the whole replay headlessly and return the finished simulation.
+    pub fn simulate(&self, data: &GameData) -> Simulation {
+        let mut sim = Simulation::new(self.seed, data);
+        for input in self.iter() {
+            sim.step(&input);
+        }
+        sim
+    }
+}
+
+/// Run every replay in `dir` and compare it against its recorded result.
+/// With `bless`, mismatching or missing results are overwritten instead.
+/// Returns the number of failing replays.
+pub fn verify_replays(dir: &Path, data: &GameData, bless: bool) -> io::Result<usize> {
+    let mut paths: Vec<_> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+
+    let mut failures = 0;
+    for path in paths {
+        let mut replay = Replay::load(&path)?;
+        let result = ReplayResult::of(&replay.simulate(data));
+        if replay.expected == Some(result) {
+            println!("ok    {} ({} ticks, {result:?})", path.display(), replay.len());
+        } else if bless {
+            println!("bless {} ({} ticks, {result:?})", path.display(), replay.len());
+            replay.expected = Some(result);
+            replay.save(&path)?;
+        } else {
+            println!("FAIL  {}: expected {:?}, got {result:?}", path.display(), replay.expected);
+            failures += 1;
+        }
+    }
+    Ok(failures)
+}
@@ -1,9 +1,12 @@
 use ggez::glam::Vec2;
+use serde::Deserialize;
 
 use crate::config::*;
-use crate::hitbox::{Hitbox, HitboxTree, HitboxNode};
-use crate::weapon::{Weapon, MachineGun, WideGun};
-use crate::spritesheet::{SpriteAnimationSystem, SpriteAnimationRegistry, SpriteObject};
+use crate::enemy::{EnemyDef, EnemyFire};
+use crate::hitbox::{Hitbox, HitboxTree, HitboxNode, SpriteHitboxes};
+use crate::movement::Movement;
+use crate::weapon::{Weapon, WeaponDef};
+use crate::spritesheet::{SpriteAnimation, SpriteAnimationSystem, SpriteAnimationRegistry, SpriteObject};
 
 /// Anything with a position and size that can participate in collision detection.
 pub trait GameObject {
@@ -12,9 +15,17 @@ pub trait GameObject {
 
     fn hitbox_tree(&self) -> Option<&HitboxTree> { None }
 
+    fn center(&self) -> Vec2 { self.position() + self.size() / 2.0 }
+
+    //position before the most recent physics step, used to interpolate rendering between ticks
+    fn previous_position(&self) -> Vec2 { self.position() }
+    fn interpolated_position(&self, alpha: f32) -> Vec2 {
+        self.previous_position().lerp(self.position(), alpha)
+    }
+
     fn collides_with(&self, other: &dyn GameObject) -> bool {
         match (self.hitbox_tree(), other.hitbox_tree()) {
-            (Some(a), Some(b)) => a.collides_with(b),
+            (Some(a), Some(b)) => a.collides_with(b).is_some(),
             _ => false,
         }
     }
@@ -32,51 +43,54 @@ pub trait GameObject {
 // =============================================================================
 
 const PLAYER_SIZE: f32 = 65.0;
+//shown before the banking animation is known, and the frame the player's first hitbox comes from
+pub const PLAYER_FRAME: &str = "PlayerBlue_Frame_01";
+//horizontal speed at which the player is drawn fully banked
+const PLAYER_FULL_BANK_SPEED: f32 = 5.0;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Player {
     pub position: Vec2,
+    pub previous_position: Vec2,
     pub velocity: Vec2,
     pub size: f32,
     pub health: f32,
-    pub experience: f32,
     pub invincibility_frames: u32,
     pub bullet_spacing: u32,
+    pub bombs: u32,
     pub hitbox_tree: HitboxTree,
     pub alive: bool,
+    //how far the ship is banked, from -1 (fully left) to 1 (fully right)
+    pub bank: f32,
+    //neutral to fully banked frames, taken from the `player_turn` animation
+    bank_animation: Option<SpriteAnimation>,
+    //frame, and whether it was mirrored, that `hitbox_tree` was built for
+    hitbox_frame: (String, bool),
     weapons: Vec<Weapon>,
     current_weapon_idx: usize,
 }
 
 impl Player {
-    pub fn new() -> Self {
+    pub fn new(config: &Config, weapons: &[WeaponDef], hitboxes: &SpriteHitboxes) -> Self {
         let pos = Vec2::new(50.0, 400.0);
         let s = PLAYER_SIZE;
         Self {
             position: pos,
+            previous_position: pos,
             velocity: Vec2::ZERO,
             size: s,
-            health: PLAYER_MAX_HEALTH,
-            experience: 0.0,
+            health: config.player_max_health,
             bullet_spacing: 0,
             invincibility_frames: 0,
-            weapons: vec![
-                Weapon::WideGun(WideGun::new()),
-                Weapon::MachineGun(MachineGun::new()),
-            ],
+            bombs: config.player_bombs,
+            weapons: weapons.iter().map(Weapon::from_def).collect(),
             current_weapon_idx: 0,
             alive: true,
-            hitbox_tree: HitboxTree::new(
-                HitboxNode::new(Hitbox::new_square(pos, s))
-                    .with_child(HitboxNode::new(Hitbox::new(
-                        pos + Vec2::new(s / 12.0, 7.0 * s / 12.0),
-                        Vec2::new(5.0 * s / 6.0, s / 4.0),
-                    )))
-                    .with_child(HitboxNode::new(Hitbox::new(
-                        pos + Vec2::new(s / 3.0, s / 6.0),
-                        Vec2::new(s / 3.0, 2.0 * s / 3.0),
-                    ))),
-            ),
+            bank: 0.0,
+            bank_animation: None,
+            //the frame's hitbox was checked for when the game data was loaded
+            hitbox_tree: hitboxes.build(PLAYER_FRAME, pos, 1.0, false).unwrap(),
+            hitbox_frame: (PLAYER_FRAME.to_string(), false),
         }
     }
 
@@ -92,22 +106,43 @@ impl Player {
         &mut self.weapons[self.current_weapon_idx]
     }
 
+    pub fn weapons(&self) -> &[Weapon] {
+        &self.weapons
+    }
+
+    pub fn weapon_idx(&self) -> usize {
+        self.current_weapon_idx
+    }
+
+    //credit a kill to the weapon at `weapon_idx`
+    pub fn gain_experience(&mut self, weapon_idx: usize, amount: f32, exp_to_level: f32) {
+        if let Some(weapon) = self.weapons.get_mut(weapon_idx) {
+            weapon.gain_experience(amount, exp_to_level);
+        }
+    }
+
+    //fire the current weapon, tagging each bullet with the weapon it came from
     pub fn shoot(&self) -> Vec<Bullet> {
-        self.weapon().fire(self)
+        let mut bullets = self.weapon().fire(self);
+        for bullet in &mut bullets {
+            bullet.source = Some(self.current_weapon_idx);
+        }
+        bullets
     }
 
-    pub fn take_damage(&mut self, amount: f32) {
+    pub fn take_damage(&mut self, amount: f32, invincibility_frames: u32) {
         self.health -= amount;
-        self.invincibility_frames = PLAYER_INVINCIBILITY_FRAMES;
+        self.invincibility_frames = invincibility_frames;
     }
 
     pub fn is_vulnerable(&self) -> bool {
         self.alive && self.invincibility_frames == 0
     }
 
-    pub fn physics(&mut self) {
+    pub fn physics(&mut self, friction: f32) {
+        self.previous_position = self.position;
         let mut new_pos = self.position + self.velocity;
-        self.velocity *= 1.0 - FRICTION;
+        self.velocity *= 1.0 - friction;
 
         new_pos.x = new_pos.x.clamp(0.0, WORLD_WIDTH - self.size);
         new_pos.y = new_pos.y.clamp(0.0, WORLD_HEIGHT - self.size);
@@ -118,18 +153,71 @@ impl Player {
 
         self.bullet_spacing = self.bullet_spacing.saturating_sub(1);
         self.invincibility_frames = self.invincibility_frames.saturating_sub(1);
+        for weapon in &mut self.weapons {
+            weapon.cool();
+        }
+        self.update_bank();
+    }
+
+    //lean towards the current horizontal velocity, one animation frame per `time_per_frame`
+    fn update_bank(&mut self) {
+        let Some(anim) = &self.bank_animation else { return };
+        let steps = (anim.frames.len() - 1).max(1) as f32;
+        let max_change = 1000.0 / TICKS_PER_SECOND as f32 / (anim.time_per_frame * steps);
+        let target = (self.velocity.x / PLAYER_FULL_BANK_SPEED).clamp(-1.0, 1.0);
+        self.bank += (target - self.bank).clamp(-max_change, max_change);
+    }
+
+    //the atlas frames bank to the right, so banking left draws them mirrored; the neutral frame never is,
+    //so the ship doesn't stay flipped while a small leftover bank settles back to 0
+    pub fn is_mirrored(&self) -> bool {
+        self.bank < 0.0 && self.bank_frame() > 0
+    }
+
+    //switch to the hitbox of the frame now on show; frames without one keep the last
+    pub fn update_hitbox(&mut self, hitboxes: &SpriteHitboxes) {
+        let Some(frame) = self.current_frame() else { return };
+        let shown = (frame, self.is_mirrored());
+        if shown == self.hitbox_frame {
+            return;
+        }
+        //the player's sprite is drawn at its pixel size
+        if let Some(tree) = hitboxes.build(&shown.0, self.position, 1.0, shown.1) {
+            self.hitbox_tree = tree;
+            self.hitbox_frame = shown;
+        }
+    }
+
+    //index into the `player_turn` frames for the current bank, 0 being the neutral frame
+    fn bank_frame(&self) -> usize {
+        self.bank_animation.as_ref()
+            .map_or(0, |anim| (self.bank.abs() * (anim.frames.len() - 1) as f32).round() as usize)
+    }
+
+    fn current_frame(&self) -> Option<String> {
+        match &self.bank_animation {
+            Some(anim) => Some(anim.frames[self.bank_frame()].clone()),
+            None => Some(PLAYER_FRAME.to_string()),
+        }
     }
 }
 
 impl GameObject for Player {
     fn position(&self) -> Vec2 { self.position }
+    fn previous_position(&self) -> Vec2 { self.previous_position }
     fn size(&self) -> Vec2 { Vec2::splat(self.size) }
     fn hitbox_tree(&self) -> Option<&HitboxTree> { Some(&self.hitbox_tree) }
 }
 
 impl SpriteObject for Player {
     fn get_frame(&self, _: &SpriteAnimationSystem) -> Option<String> {
-        Some("PlayerBlue_Frame_01".to_string())
+        self.current_frame()
+    }
+
+    fn register_in_system(&mut self, _: &mut SpriteAnimationSystem, animation_registry: &SpriteAnimationRegistry) {
+        self.bank_animation = animation_registry.get_anim("player_turn".to_string())
+            .filter(|anim| !anim.frames.is_empty())
+            .cloned();
     }
 }
 
@@ -137,45 +225,172 @@ impl SpriteObject for Player {
 // Enemy
 // =============================================================================
 
-const ENEMY_SIZE: f32 = 60.0;
+//enemy size at which atlas frames are drawn unscaled
+const ENEMY_SPRITE_SIZE: f32 = 60.0;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Enemy {
+    //unique within a run, so other objects can refer to an enemy across ticks
+    pub id: u32,
     pub position: Vec2,
+    pub previous_position: Vec2,
     pub velocity: Vec2,
     pub size: f32,
     pub health: f32,
     pub flash_frames: i32,
+    //index of the player weapon that last damaged this enemy, which gets the kill
+    pub last_hit_by: Option<usize>,
     pub alive: bool,
     pub hitbox_tree: HitboxTree,
+    //sprite frame `hitbox_tree` was built for, if it came from one
+    hitbox_frame: Option<String>,
+    pub movement: Movement,
+    pub def: EnemyDef,
+    //remaining health of each of `def.parts`
+    pub part_health: Vec<f32>,
+    //parts destroyed since the simulation last looked, so it can reward them
+    pub broken_parts: Vec<usize>,
+    pub anim_handle: Option<usize>,
+    //set while the hurt animation plays over the idle one
+    pub hurt_anim_handle: Option<usize>,
 }
 
 impl Enemy {
-    pub fn new(position: Vec2) -> Self {
-        let s = ENEMY_SIZE;
-        Self {
+    pub fn new(
+        id: u32,
+        def: &EnemyDef,
+        movement: Movement,
+        sprite_system: &mut SpriteAnimationSystem,
+        animation_registry: &SpriteAnimationRegistry,
+        hitboxes: &SpriteHitboxes,
+    ) -> Self {
+        let position = movement.position();
+        let mut enemy = Self {
+            id,
             position,
-            velocity: Vec2::new(0.0, 0.03),
-            size: s,
-            health: 80.0,
+            previous_position: position,
+            velocity: Vec2::ZERO,
+            size: def.size,
+            health: def.health,
             flash_frames: 0,
+            last_hit_by: None,
             alive: true,
-            hitbox_tree: HitboxTree::new(
-                HitboxNode::new(Hitbox::new_square(position, s))
-                    .with_child(HitboxNode::new(Hitbox::new(
-                        position + Vec2::new(0.0, 2.0 * s / 5.0),
-                        Vec2::new(s, s / 5.0),
-                    )))
-                    .with_child(HitboxNode::new(Hitbox::new(
-                        position + Vec2::new(3.0 * s / 10.0, s / 5.0),
-                        Vec2::new(2.0 * s / 5.0, 3.0 * s / 5.0),
-                    ))),
-            ),
+            //replaced below, by the enemy type's own hitbox or its first frame's
+            hitbox_tree: HitboxTree::new(HitboxNode::new(Hitbox::new_square(position, def.size))),
+            hitbox_frame: None,
+            movement,
+            def: def.clone(),
+            part_health: def.parts.iter().map(|part| part.health).collect(),
+            broken_parts: Vec::new(),
+            anim_handle: None,
+            hurt_anim_handle: None,
+        };
+        enemy.register_in_system(sprite_system, animation_registry);
+        match &def.hitbox {
+            Some(layout) => enemy.hitbox_tree = enemy.with_parts(layout.build(position, Vec2::splat(def.size))),
+            None => enemy.update_hitbox(sprite_system, hitboxes),
         }
+        enemy
     }
 
-    pub fn physics(&mut self) {
-        self.position += self.velocity;
+    //switch to the hitbox of the frame now on show, unless the enemy type has its own
+    pub fn update_hitbox(&mut self, sprite_system: &SpriteAnimationSystem, hitboxes: &SpriteHitboxes) {
+        let frame = self.get_frame(sprite_system);
+        if self.def.hitbox.is_some() || frame.is_none() || frame == self.hitbox_frame {
+            return;
+        }
+        if let Some(hull) = frame.as_deref().and_then(|f| hitboxes.build(f, self.position, self.sprite_scale(), false)) {
+            self.hitbox_tree = self.with_parts(hull);
+            self.hitbox_frame = frame;
+        }
+    }
+
+    //attach every part that is still standing under the root of `hull`
+    fn with_parts(&self, mut hull: HitboxTree) -> HitboxTree {
+        let size = Vec2::splat(self.size);
+        for (i, part) in self.def.parts.iter().enumerate() {
+            if self.part_health[i] > 0.0 {
+                hull.add_subtree(part.hitbox.build_node(self.position, size).with_tag(i));
+            }
+        }
+        hull
+    }
+
+    //start the hurt animation on a fresh hit, and drop it once it has played out; a hit landing
+    //as it finishes restarts it straight away so it never flickers back to idle in between
+    pub fn animate(&mut self, sprite_system: &mut SpriteAnimationSystem, animation_registry: &SpriteAnimationRegistry) {
+        if let Some(handle) = self.hurt_anim_handle {
+            if sprite_system.get_anim(handle).is_none_or(|anim| anim.finished) {
+                sprite_system.release(handle);
+                self.hurt_anim_handle = None;
+            }
+        }
+        if self.hurt_anim_handle.is_none() && self.flash_frames > 0 {
+            self.hurt_anim_handle = sprite_system.add_registered_anim(self.def.hurt_key(), animation_registry);
+        }
+    }
+
+    //hand this enemy's animations back to the system once it is gone
+    pub fn release_animations(&mut self, sprite_system: &mut SpriteAnimationSystem) {
+        for handle in [self.anim_handle.take(), self.hurt_anim_handle.take()].into_iter().flatten() {
+            sprite_system.release(handle);
+        }
+    }
+
+    pub fn sprite_scale(&self) -> f32 {
+        self.size / ENEMY_SPRITE_SIZE
+    }
+
+    //damage the part tagged `part`, or the hull if there is none or it is already destroyed
+    pub fn take_damage(&mut self, amount: f32, part: Option<usize>) {
+        match part.filter(|&i| self.part_health.get(i).is_some_and(|&health| health > 0.0)) {
+            Some(i) => {
+                self.part_health[i] -= amount;
+                if self.part_health[i] <= 0.0 {
+                    self.hitbox_tree.remove_tagged(i);
+                    self.broken_parts.push(i);
+                }
+            }
+            None => self.health -= amount,
+        }
+        self.flash_frames = 5;
+    }
+
+    //hull and part health together, as a fraction of what the enemy spawned with
+    pub fn health_fraction(&self) -> f32 {
+        let total = |hull: f32, parts: f32| hull.max(0.0) + parts;
+        let current = total(self.health, self.part_health.iter().map(|h| h.max(0.0)).sum());
+        let max = total(self.def.health, self.def.parts.iter().map(|part| part.health).sum());
+        current / max
+    }
+
+    pub fn part_center(&self, part: usize) -> Vec2 {
+        self.def.parts[part].hitbox.center(self.position, Vec2::splat(self.size))
+    }
+
+    //a bullet fired with `fire`'s settings, leaving from just below the enemy's center
+    pub fn shoot(&self, fire: &EnemyFire, velocity: Vec2) -> Bullet {
+        let offset = Vec2::new(0.0, self.size / 3.0);
+        Bullet::new(self, velocity, Some(offset), fire.damage, fire.bullet_type.clone())
+            .with_size(fire.bullet_size)
+    }
+
+    //how the enemy shoots in its current attack phase
+    pub fn fire(&self) -> Option<&EnemyFire> {
+        let destroyed = self.part_health.iter().filter(|&&health| health <= 0.0).count();
+        match destroyed.checked_sub(1) {
+            Some(phase) => self.def.phases.get(phase).or(self.def.phases.last()).or(self.def.fire.as_ref()),
+            None => self.def.fire.as_ref(),
+        }
+    }
+
+    //`target` is the point to dive at (the player's center), if attacking is allowed
+    pub fn physics(&mut self, target: Option<Vec2>) {
+        self.previous_position = self.position;
+        let new_pos = self.movement.step(target.map(|t| t - Vec2::splat(self.size / 2.0)));
+        self.velocity = new_pos - self.position;
+
+        self.position = new_pos;
         self.hitbox_tree.move_delta(self.velocity);
         self.flash_frames = (self.flash_frames - 1).max(0);
     }
@@ -183,13 +398,24 @@ impl Enemy {
 
 impl GameObject for Enemy {
     fn position(&self) -> Vec2 { self.position }
+    fn previous_position(&self) -> Vec2 { self.previous_position }
     fn size(&self) -> Vec2 { Vec2::splat(self.size) }
     fn hitbox_tree(&self) -> Option<&HitboxTree> { Some(&self.hitbox_tree) }
 }
 
 impl SpriteObject for Enemy {
-    fn get_frame(&self, _: &SpriteAnimationSystem) -> Option<String> {
-        Some("Enemy01_Red_Frame_1".to_string())
+    fn get_frame(&self, sprite_system: &SpriteAnimationSystem) -> Option<String> {
+        self.hurt_anim_handle.or(self.anim_handle)
+            .and_then(|handle| sprite_system.get_frame(handle))
+            .cloned()
+    }
+
+    fn register_in_system(
+        &mut self,
+        sprite_system: &mut SpriteAnimationSystem,
+        animation_registry: &SpriteAnimationRegistry,
+    ) {
+        self.anim_handle = sprite_system.add_registered_anim(self.def.idle_key(), animation_registry);
     }
 }
 
@@ -197,18 +423,75 @@ impl SpriteObject for Enemy {
 // Bullet
 // =============================================================================
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum BulletType {
     Minigun,
     Laser,
     Proton,
+    Plasma,
+}
+
+/// Size tier of a bullet, which scales its hitbox and damage and picks its sprite frame.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BulletSize {
+    #[default]
+    Small,
+    Medium,
+    Large,
+}
+
+impl BulletSize {
+    //tiers past `Large` are clamped to `Large`
+    pub fn from_index(index: u32) -> BulletSize {
+        match index {
+            0 => BulletSize::Small,
+            1 => BulletSize::Medium,
+            _ => BulletSize::Large,
+        }
+    }
+    pub fn index(self) -> u32 {
+        self as u32
+    }
+    //roughly how much larger each tier's sprites are drawn in the atlas
+    pub fn scale(self) -> f32 {
+        match self {
+            BulletSize::Small => 1.0,
+            BulletSize::Medium => 1.25,
+            BulletSize::Large => 1.5,
+        }
+    }
+    pub fn damage_multiplier(self) -> f32 {
+        match self {
+            BulletSize::Small => 1.0,
+            BulletSize::Medium => 1.5,
+            BulletSize::Large => 2.0,
+        }
+    }
+    fn frame_suffix(self) -> &'static str {
+        match self {
+            BulletSize::Small => "Small",
+            BulletSize::Medium => "Medium",
+            BulletSize::Large => "Large",
+        }
+    }
 }
 
 const BULLET_SIZE: f32 = 10.0;
 
+/// Steering state for a bullet that chases enemies instead of flying straight.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Homing {
+    //largest change of heading per tick, in radians
+    pub turn_rate: f32,
+    pub target: Option<u32>,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Bullet {
     pub position: Vec2,
+    pub previous_position: Vec2,
     pub velocity: Vec2,
     pub angle: f32,
     pub size: f32,
@@ -216,6 +499,10 @@ pub struct Bullet {
     pub alive: bool,
     pub hitbox_tree: HitboxTree,
     pub bullet_type: BulletType,
+    pub bullet_size: BulletSize,
+    pub homing: Option<Homing>,
+    //index of the player weapon that fired this bullet; None for enemy bullets
+    pub source: Option<usize>,
 }
 
 impl Bullet {
@@ -232,41 +519,171 @@ impl Bullet {
         );
         let final_offset = default_offset + offset.unwrap_or(Vec2::ZERO);
         let pos = origin.position() + final_offset;
+        let angle = velocity.x.atan2(-velocity.y);
+        let hitbox_tree = Self::hitbox_tree_at(pos, BULLET_SIZE, angle, &bullet_type);
 
         Self {
             position: pos,
+            previous_position: pos,
             velocity,
-            angle: velocity.x.atan2(-velocity.y),
+            angle,
             size: BULLET_SIZE,
             damage,
             alive: true,
             bullet_type,
-            hitbox_tree: HitboxTree::new(HitboxNode::new(Hitbox::new(
-                pos + Vec2::new(BULLET_SIZE / 8.0, 0.0),
-                Vec2::splat(3.0 * BULLET_SIZE / 4.0),
-            ))),
+            bullet_size: BulletSize::Small,
+            homing: None,
+            source: None,
+            hitbox_tree,
         }
     }
 
+    //grow or shrink the bullet around its center, scaling its damage along with it
+    pub fn with_size(mut self, bullet_size: BulletSize) -> Self {
+        let size = BULLET_SIZE * bullet_size.scale();
+        self.position += Vec2::splat((self.size - size) / 2.0);
+        self.previous_position = self.position;
+        self.size = size;
+        self.damage *= bullet_size.damage_multiplier();
+        self.bullet_size = bullet_size;
+        self.hitbox_tree = Self::hitbox_tree_at(self.position, size, self.angle, &self.bullet_type);
+        self
+    }
+
+    pub fn with_homing(mut self, turn_rate: f32) -> Self {
+        self.homing = Some(Homing { turn_rate, target: None });
+        self
+    }
+
+    //turn a homing bullet toward its target, picking the nearest enemy if it has none or its target died
+    pub fn steer(&mut self, enemies: &[Enemy]) {
+        let center = self.center();
+        let Some(homing) = &mut self.homing else { return };
+        let distance = |e: &Enemy| e.center().distance_squared(center);
+        let target = homing.target
+            .and_then(|id| enemies.iter().find(|e| e.id == id && e.alive))
+            .or_else(|| enemies.iter().filter(|e| e.alive).min_by(|a, b| distance(a).total_cmp(&distance(b))));
+        homing.target = target.map(|e| e.id);
+
+        if let Some(target) = target {
+            let desired = target.center() - center;
+            if desired != Vec2::ZERO {
+                let turn = self.velocity.angle_between(desired).clamp(-homing.turn_rate, homing.turn_rate);
+                self.velocity = Vec2::from_angle(turn).rotate(self.velocity);
+                self.angle = self.velocity.x.atan2(-self.velocity.y);
+                self.hitbox_tree = Self::hitbox_tree_at(self.position, self.size, self.angle, &self.bullet_type);
+            }
+        }
+    }
+
+    //sprites are drawn turned by `angle` around their top left corner, so the hitbox turns the same way.
+    //Round shots get a round hitbox
+    fn hitbox_tree_at(pos: Vec2, size: f32, angle: f32, bullet_type: &BulletType) -> HitboxTree {
+        let half_size = Vec2::splat(3.0 * size / 8.0);
+        let center = pos + Vec2::from_angle(angle).rotate(Vec2::new(size / 2.0, half_size.y));
+        let hitbox = match bullet_type {
+            BulletType::Proton | BulletType::Plasma => Hitbox::circle(center, half_size.x),
+            BulletType::Minigun | BulletType::Laser => Hitbox::oriented_rect(center, half_size, angle),
+        };
+        HitboxTree::new(HitboxNode::new(hitbox))
+    }
+
     pub fn physics(&mut self) {
+        self.previous_position = self.position;
         self.position += self.velocity;
         self.hitbox_tree.move_delta(self.velocity);
     }
+    //how far the last physics step moved this bullet
+    pub fn displacement(&self) -> Vec2 {
+        self.position - self.previous_position
+    }
 }
 
 impl GameObject for Bullet {
     fn position(&self) -> Vec2 { self.position }
+    fn previous_position(&self) -> Vec2 { self.previous_position }
     fn size(&self) -> Vec2 { Vec2::splat(self.size) }
     fn hitbox_tree(&self) -> Option<&HitboxTree> { Some(&self.hitbox_tree) }
 }
 
 impl SpriteObject for Bullet {
     fn get_frame(&self, _: &SpriteAnimationSystem) -> Option<String> {
-        Some(match self.bullet_type {
-            BulletType::Minigun => "Minigun_Small",
-            BulletType::Laser => "Laser_Small",
-            BulletType::Proton => "Proton_Medium",
-        }.to_string())
+        let prefix = match self.bullet_type {
+            BulletType::Minigun => "Minigun",
+            BulletType::Laser => "Laser",
+            BulletType::Proton => "Proton",
+            BulletType::Plasma => "Plasma",
+        };
+        Some(format!("{prefix}_{}", self.bullet_size.frame_suffix()))
+    }
+}
+
+// =============================================================================
+// Beam
+// =============================================================================
+
+/// A continuous laser held on by the player. Rebuilt every tick it fires, reaching
+/// straight up from `origin` until it hits the first enemy or runs out of `range`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Beam {
+    pub origin: Vec2,
+    pub range: f32,
+    pub length: f32,
+    pub width: f32,
+    pub damage: f32,
+}
+
+impl Beam {
+    pub fn new(origin: Vec2, range: f32, width: f32, damage: f32) -> Self {
+        Self { origin, range, length: range, width, damage }
+    }
+
+    //cut the beam off at the nearest enemy in its path and return that enemy's index,
+    //along with the tag of the part it hit
+    pub fn cast(&mut self, enemies: &[Enemy]) -> Option<(usize, Option<usize>)> {
+        let end = self.origin - Vec2::new(0.0, self.range);
+        let hit = enemies.iter()
+            .enumerate()
+            .filter_map(|(i, e)| e.hitbox_tree.segment_hit(self.origin, end, self.width).map(|(t, tag)| (i, t, tag)))
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        self.length = hit.map_or(self.range, |(_, t, _)| t * self.range);
+        hit.map(|(i, _, tag)| (i, tag))
+    }
+}
+
+impl GameObject for Beam {
+    fn position(&self) -> Vec2 { self.origin - Vec2::new(self.width / 2.0, self.length) }
+    fn size(&self) -> Vec2 { Vec2::new(self.width, self.length) }
+}
+
+impl SpriteObject for Beam {
+    fn get_frame(&self, _: &SpriteAnimationSystem) -> Option<String> {
+        Some("Laser_Large".to_string())
+    }
+}
+
+// =============================================================================
+// Blast (bomb shockwave)
+// =============================================================================
+
+const BLAST_FRAMES: u32 = 30;
+
+/// Purely visual ring left by a bomb; its effects are applied the moment it detonates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Blast {
+    pub center: Vec2,
+    pub radius: f32,
+    pub frames_left: u32,
+}
+
+impl Blast {
+    pub fn new(center: Vec2, radius: f32) -> Self {
+        Self { center, radius, frames_left: BLAST_FRAMES }
+    }
+
+    //fraction of the blast's lifetime remaining, from 1 (just detonated) down to 0
+    pub fn strength(&self) -> f32 {
+        self.frames_left as f32 / BLAST_FRAMES as f32
     }
 }
 
@@ -276,6 +693,7 @@ impl SpriteObject for Bullet {
 
 pub struct Star {
     pub position: Vec2,
+    pub previous_position: Vec2,
     pub velocity: Vec2,
     pub size: f32,
     pub brightness: f32,
@@ -283,16 +701,18 @@ pub struct Star {
 
 impl Star {
     pub fn new(position: Vec2, velocity: Vec2, size: f32, brightness: f32) -> Self {
-        Self { position, velocity, size, brightness }
+        Self { position, previous_position: position, velocity, size, brightness }
     }
 
     pub fn physics(&mut self) {
+        self.previous_position = self.position;
         self.position += self.velocity;
     }
 }
 
 impl GameObject for Star {
     fn position(&self) -> Vec2 { self.position }
+    fn previous_position(&self) -> Vec2 { self.previous_position }
     fn size(&self) -> Vec2 { Vec2::splat(self.size) }
 
     fn is_off_screen(&self) -> bool {
@@ -333,7 +753,7 @@ impl Explosion {
 
     pub fn poll_animation_finished(&self, anim_system: &SpriteAnimationSystem) -> bool {
         anim_system.get_anim(self.anim_handle)
-            .map_or(false, |anim| anim.finished)
+            .is_some_and(|anim| anim.finished)
     }
 }
 
@@ -0,0 +1,552 @@
+use ggez::glam::Vec2;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use rand_distr::StandardNormal;
+
+use crate::broadphase::{Broadphase, Layer};
+use crate::config::*;
+use crate::data::GameData;
+use crate::spritesheet::{SpriteAnimation, SpriteAnimationSystem, SpriteAnimationRegistry, SpriteObject};
+use crate::weapon::FireMode;
+use crate::enemy::{EnemyDef, EnemyFire};
+use crate::movement::{Drift, Movement, Pattern};
+use crate::wave::{generate_wave, stage_rng, SpawnEvent, WaveScript};
+use crate::hitbox::{SpriteHitboxes, TreeHit};
+use crate::shooter::{Player, Enemy, Beam, Blast, Bullet, Star, GameObject, Explosion};
+
+/// Snapshot of the player's controls for a single tick.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Input {
+    pub up: bool,
+    pub down: bool,
+    pub left: bool,
+    pub right: bool,
+    pub fire: bool,
+    pub cycle_weapon: bool,
+    pub bomb: bool,
+}
+
+impl Input {
+    //pack into a bitmask (one bit per control) for compact replay storage
+    pub fn to_bits(self) -> u8 {
+        [self.up, self.down, self.left, self.right, self.fire, self.cycle_weapon, self.bomb]
+            .iter()
+            .enumerate()
+            .fold(0, |bits, (i, &pressed)| bits | (pressed as u8) << i)
+    }
+    pub fn from_bits(bits: u8) -> Input {
+        let pressed = |i: u8| bits & (1 << i) != 0;
+        Input {
+            up: pressed(0),
+            down: pressed(1),
+            left: pressed(2),
+            right: pressed(3),
+            fire: pressed(4),
+            cycle_weapon: pressed(5),
+            bomb: pressed(6),
+        }
+    }
+}
+
+/// All game logic, independent of any window or graphics context.
+/// Advanced one tick at a time with `step`.
+pub struct Simulation {
+    pub player: Player,
+    pub bullets: Vec<Bullet>,
+    pub enemy_bullets: Vec<Bullet>,
+    //the player's beam weapon, if it is firing this tick
+    pub beam: Option<Beam>,
+    //shockwave of the most recent bomb, while it is still visible
+    pub blast: Option<Blast>,
+    pub enemies: Vec<Enemy>,
+    pub explosions: Vec<Explosion>,
+    pub stars: Vec<Star>,
+    pub stage: usize,
+    pub score: u32,
+    pub no_attack_timer: usize,
+    pub tick_count: usize,
+    next_enemy_id: u32,
+    //controls held last tick, so single presses can be told apart from holds
+    last_input: Input,
+    pub status: Option<&'static str>,
+    pub seed: u64,
+    pub config: Config,
+    enemy_defs: Vec<EnemyDef>,
+    waves: Vec<WaveScript>,
+    //enemies still to come this stage: (tick due, enemy type as adjusted by its spawn event, movement)
+    scheduled: Vec<(usize, EnemyDef, Movement)>,
+    //which objects are close enough to be worth an exact collision test
+    broadphase: Broadphase,
+    pub animation_system: SpriteAnimationSystem,
+    animation_registry: SpriteAnimationRegistry,
+    hitboxes: SpriteHitboxes,
+    //every random decision draws from this, so a seed plus an input stream fully determines a run
+    rng: StdRng,
+}
+
+impl Simulation {
+    pub fn new(seed: u64, data: &GameData) -> Self {
+        let mut animation_registry = SpriteAnimationRegistry::new();
+        animation_registry.add_anim(
+            "explosion".to_string(),
+            SpriteAnimation::new(
+                (1..=9).map(|i| format!("Explosion01_Frame_{i:02}")).collect(),
+                1000.0 / 24.0,
+                false,
+            ),
+        );
+        animation_registry.add_anim(
+            "player_turn".to_string(),
+            SpriteAnimation::new(
+                (1..=3).map(|i| format!("PlayerBlue_Frame_{i:02}")).collect(),
+                1000.0 / 12.0,
+                false,
+            ),
+        );
+        for def in &data.enemies {
+            animation_registry.add_anim(def.idle_key(), def.idle.clone());
+            if let Some(hurt) = &def.hurt {
+                animation_registry.add_anim(def.hurt_key(), hurt.clone());
+            }
+        }
+
+        let mut animation_system = SpriteAnimationSystem::new();
+        let mut player = Player::new(&data.config, &data.weapons, &data.hitboxes);
+        player.register_in_system(&mut animation_system, &animation_registry);
+
+        Self {
+            player,
+            bullets: Vec::new(),
+            enemy_bullets: Vec::new(),
+            beam: None,
+            blast: None,
+            enemies: Vec::new(),
+            explosions: Vec::new(),
+            stars: Vec::new(),
+            stage: 0,
+            score: 0,
+            no_attack_timer: 0,
+            tick_count: 0,
+            next_enemy_id: 0,
+            last_input: Input::default(),
+            status: None,
+            seed,
+            config: data.config.clone(),
+            enemy_defs: data.enemies.clone(),
+            waves: data.waves.clone(),
+            scheduled: Vec::new(),
+            broadphase: Broadphase::new(),
+            animation_system,
+            animation_registry,
+            hitboxes: data.hitboxes.clone(),
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// Advance the game by one fixed tick of 1/TICKS_PER_SECOND seconds.
+    pub fn step(&mut self, input: &Input) {
+        self.tick_count += 1;
+        self.no_attack_timer = self.no_attack_timer.saturating_sub(1);
+
+        self.handle_input(input);
+        self.last_input = *input;
+        self.handle_bullets();
+        self.handle_enemies();
+        self.handle_background();
+
+        // Expire finished explosions
+        for exp in &mut self.explosions {
+            if exp.poll_animation_finished(&self.animation_system) {
+                exp.finished = true;
+                self.animation_system.release(exp.anim_handle);
+            }
+        }
+        self.explosions.retain(|exp| !exp.finished);
+
+        // Fade out the last bomb blast
+        if let Some(blast) = &mut self.blast {
+            blast.frames_left = blast.frames_left.saturating_sub(1);
+            if blast.frames_left == 0 {
+                self.blast = None;
+            }
+        }
+
+        // Advance animations
+        self.animation_system.time_tick(1000.0 / TICKS_PER_SECOND as f32);
+
+        // Player physics
+        self.player.physics(self.config.friction);
+        self.player.update_hitbox(&self.hitboxes);
+
+        // Wave progression
+        self.spawn_scheduled();
+        if self.player.alive && self.enemies.is_empty() && self.scheduled.is_empty() {
+            self.spawn_wave();
+        }
+
+        // Death check
+        if self.player.alive && self.player.health <= 0.0 {
+            self.handle_player_death();
+        }
+    }
+
+    // -- Input ----------------------------------------------------------------
+
+    fn handle_input(&mut self, input: &Input) {
+        if input.up    { self.player.velocity += Vec2::new(0.0, -1.0); }
+        if input.down  { self.player.velocity += Vec2::new(0.0,  1.0); }
+        if input.left  { self.player.velocity += Vec2::new(-1.0, 0.0); }
+        if input.right { self.player.velocity += Vec2::new( 1.0, 0.0); }
+
+        if input.cycle_weapon && self.player.alive && self.player.bullet_spacing == 0 {
+            self.player.cycle_weapons();
+            self.player.bullet_spacing += 50;
+        }
+        if input.bomb && !self.last_input.bomb && self.player.alive && self.player.bombs > 0 {
+            self.detonate_bomb();
+        }
+
+        let ready = self.player.alive && self.player.bullet_spacing == 0 && self.no_attack_timer == 0
+            && !self.player.weapon().is_overheated();
+        self.beam = None;
+        match self.player.weapon().fire_mode() {
+            FireMode::Burst => {
+                if input.fire && ready {
+                    self.fire_weapon();
+                }
+            }
+            FireMode::Charge => {
+                if input.fire && ready {
+                    self.player.weapon_mut().charge();
+                } else if !input.fire && ready && self.player.weapon().charge_fraction() > 0.0 {
+                    self.fire_weapon();
+                }
+            }
+            FireMode::Beam => {
+                if input.fire && ready {
+                    self.beam = self.player.weapon().beam(&self.player);
+                    self.player.weapon_mut().discharge();
+                }
+            }
+        }
+    }
+
+    //clear enemy bullets around the player, damage every enemy in range, and grant brief invincibility
+    fn detonate_bomb(&mut self) {
+        let center = self.player.center();
+        let radius = self.config.bomb_radius;
+        self.player.bombs -= 1;
+        self.player.invincibility_frames = self.player.invincibility_frames.max(self.config.bomb_invincibility_frames);
+
+        self.enemy_bullets.retain(|b| b.center().distance(center) > radius);
+        for enemy in &mut self.enemies {
+            if enemy.center().distance(center) <= radius {
+                enemy.take_damage(self.config.bomb_damage, None);
+            }
+        }
+        self.blast = Some(Blast::new(center, radius));
+    }
+
+    fn fire_weapon(&mut self) {
+        self.bullets.extend(self.player.shoot());
+        self.player.weapon_mut().discharge();
+        self.player.bullet_spacing = self.player.weapon().fire_rate().burst;
+    }
+
+    // -- Physics & Collisions -------------------------------------------------
+
+    fn handle_bullets(&mut self) {
+        let no_attack = self.no_attack_timer > 0;
+
+        for bullet in &mut self.bullets {
+            bullet.steer(&self.enemies);
+            bullet.physics();
+        }
+        for bullet in &mut self.enemy_bullets {
+            bullet.physics();
+        }
+        self.rebuild_broadphase();
+
+        // Player bullets vs the first enemy along the way they moved this tick
+        if !no_attack {
+            let mut first_hits: Vec<(usize, usize, f32, TreeHit)> = Vec::new();
+            for (b, e) in self.broadphase.pairs(Layer::PlayerBullets, Layer::Enemies) {
+                let bullet = &self.bullets[b];
+                let Some((t, hit)) = self.enemies[e].hitbox_tree.sweep_hit(&bullet.hitbox_tree, bullet.displacement()) else {
+                    continue;
+                };
+                //pairs come sorted by bullet, so an earlier hit by the same bullet is the last one found
+                match first_hits.last_mut() {
+                    Some(last) if last.0 == b => {
+                        if t < last.2 {
+                            *last = (b, e, t, hit);
+                        }
+                    }
+                    _ => first_hits.push((b, e, t, hit)),
+                }
+            }
+            for (b, e, _, hit) in first_hits {
+                let (bullet, enemy) = (&mut self.bullets[b], &mut self.enemies[e]);
+                bullet.alive = false;
+                enemy.take_damage(bullet.damage, hit.own_tag);
+                enemy.last_hit_by = bullet.source;
+            }
+        }
+        self.bullets.retain(|b| b.alive && !b.is_off_screen());
+
+        // Player beam vs the first enemy in its path
+        if let Some(beam) = &mut self.beam {
+            if let Some((i, part)) = beam.cast(&self.enemies) {
+                let enemy = &mut self.enemies[i];
+                enemy.take_damage(beam.damage, part);
+                enemy.last_hit_by = Some(self.player.weapon_idx());
+            }
+        }
+
+        // Enemy bullets vs player
+        if self.player.is_vulnerable() && !no_attack {
+            for (b, _) in self.broadphase.pairs(Layer::EnemyBullets, Layer::Player) {
+                let bullet = &mut self.enemy_bullets[b];
+                if self.player.hitbox_tree.sweep_hit(&bullet.hitbox_tree, bullet.displacement()).is_some() {
+                    bullet.alive = false;
+                    self.player.take_damage(bullet.damage, self.config.player_invincibility_frames);
+                }
+            }
+        }
+        self.enemy_bullets.retain(|b| b.alive && !b.is_off_screen());
+    }
+
+    fn handle_enemies(&mut self) {
+        let num_enemies = self.enemies.len();
+        let no_attack = self.no_attack_timer > 0;
+        let dive_target = (self.player.alive && !no_attack).then(|| self.player.center());
+
+        for enemy in &mut self.enemies {
+            enemy.physics(dive_target);
+            enemy.animate(&mut self.animation_system, &self.animation_registry);
+            enemy.update_hitbox(&self.animation_system, &self.hitboxes);
+
+            // Enemy shooting: on a pattern's schedule, or single aimed shots at random
+            match enemy.fire().filter(|_| self.player.alive && !no_attack) {
+                Some(fire @ EnemyFire { pattern: Some(pattern), .. }) => {
+                    for direction in pattern.fire(enemy.movement.age(), enemy.center(), self.player.center()) {
+                        self.enemy_bullets.push(enemy.shoot(fire, direction * fire.speed));
+                    }
+                }
+                Some(fire) => {
+                    let scaled_chance = (self.config.enemy_shoot_chance * num_enemies * num_enemies / self.stage).max(1);
+                    if self.rng.gen_range(0.0..scaled_chance as f32) < fire.rate {
+                        let direction = self.player.position - enemy.position;
+                        let dist = direction.length();
+                        let accuracy = 1.0 / dist / num_enemies as f32;
+                        let normal_sample: f32 = self.rng.sample(StandardNormal);
+                        let noise = fire.spread * self.player.size / 2.0 * normal_sample * (1.0 - accuracy) * Vec2::ONE;
+                        let velocity = (direction + noise).normalize() * fire.speed;
+                        self.enemy_bullets.push(enemy.shoot(fire, velocity));
+                    }
+                }
+                None => {}
+            }
+
+            // Destroyed parts
+            for part in std::mem::take(&mut enemy.broken_parts) {
+                self.score += enemy.def.parts[part].score;
+                let size = 64.0 * enemy.sprite_scale() / 3.0;
+                self.explosions.push(Explosion::new(
+                    enemy.part_center(part) - Vec2::splat(size / 2.0), size,
+                    &mut self.animation_system, &self.animation_registry,
+                ));
+            }
+
+            // Enemy death
+            if enemy.health <= 0.0 {
+                enemy.alive = false;
+                enemy.release_animations(&mut self.animation_system);
+                self.score += enemy.def.score;
+                if let Some(weapon_idx) = enemy.last_hit_by {
+                    self.player.gain_experience(weapon_idx, enemy.def.experience, self.config.exp_to_level);
+                }
+            }
+        }
+
+        // Contact damage, against the enemies' new positions; enemies killed earlier this tick no longer count
+        self.rebuild_broadphase();
+        if !no_attack {
+            for (e, _) in self.broadphase.pairs(Layer::Enemies, Layer::Player) {
+                let enemy = &self.enemies[e];
+                if enemy.alive && self.player.is_vulnerable() && enemy.collides_with(&self.player) {
+                    self.player.take_damage(self.config.player_contact_damage, self.config.player_invincibility_frames);
+                }
+            }
+        }
+
+        // Spawn explosions for dead enemies
+        let dead_enemies: Vec<_> = self.enemies.iter()
+            .filter(|e| !e.alive)
+            .map(|e| (e.position, 64.0 * e.sprite_scale()))
+            .collect();
+        for (pos, size) in dead_enemies {
+            self.explosions.push(Explosion::new(
+                pos, size, &mut self.animation_system, &self.animation_registry,
+            ));
+        }
+
+        self.enemies.retain(|e| e.alive);
+    }
+
+    //index everything that can collide by where it is now
+    fn rebuild_broadphase(&mut self) {
+        //bullets are indexed over the whole stretch they moved this tick, so they are swept into what they passed
+        let swept = |b: &Bullet| b.hitbox_tree.bounds().swept(b.displacement());
+        self.broadphase.clear();
+        self.broadphase.insert_all(Layer::Player, std::iter::once(self.player.hitbox_tree.bounds().clone()));
+        self.broadphase.insert_all(Layer::Enemies, self.enemies.iter().map(|e| e.hitbox_tree.bounds().clone()));
+        self.broadphase.insert_all(Layer::PlayerBullets, self.bullets.iter().map(swept));
+        self.broadphase.insert_all(Layer::EnemyBullets, self.enemy_bullets.iter().map(swept));
+    }
+
+    fn handle_background(&mut self) {
+        if self.stars.len() < self.config.num_stars && self.rng.gen_range(0.0..1.0_f32) < 0.3 {
+            let x = self.rng.gen_range(0.0..DISPLAY_WIDTH);
+            let normal_sample: f32 = self.rng.sample(StandardNormal);
+            let brightness: f32 = self.rng.gen_range(0.0..1.0);
+            let size = 1.0 + 0.5 * normal_sample.abs();
+            let speed = 0.3 + 0.3 * brightness + 0.3 * normal_sample.abs();
+            self.stars.push(Star::new(
+                Vec2::new(x, 0.0),
+                Vec2::new(0.0, speed),
+                size,
+                brightness,
+            ));
+        }
+
+        for star in &mut self.stars {
+            if star.position.y > DISPLAY_HEIGHT {
+                star.position.y = 0.0;
+            }
+            star.physics();
+        }
+    }
+
+    // -- Wave management ------------------------------------------------------
+
+    fn spawn_wave(&mut self) {
+        self.stage += 1;
+        self.no_attack_timer = self.config.wave_grace_period;
+
+        if self.player.health < self.config.player_min_health_restore {
+            self.player.health = self.config.player_min_health_restore;
+        }
+        self.player.bombs = self.config.player_bombs;
+
+        // Authored stages come first; once they run out, waves are generated
+        let script = match self.waves.get(self.stage - 1) {
+            Some(script) => {
+                let mut script = script.clone();
+                script.stagger_dives(&mut stage_rng(self.stage, self.seed));
+                script
+            }
+            None => generate_wave(self.stage, self.seed, &self.enemy_defs, &self.config),
+        };
+        for event in &script.events {
+            self.schedule_event(event);
+        }
+    }
+
+    //queue every member of a scripted group to spawn at its time
+    fn schedule_event(&mut self, event: &SpawnEvent) {
+        //names were checked against the enemy types when the scripts were loaded
+        let mut def = self.enemy_defs.iter().find(|def| def.name == event.enemy).unwrap().clone();
+        def = def.with_health_scale(event.health);
+        if let Some(fire) = &event.fire {
+            def.fire = Some(fire.clone());
+        }
+        let pattern = event.movement.clone().unwrap_or_else(|| def.movement.clone());
+        let position = Vec2::from(event.position);
+        let entry = event.entry.map_or(Vec2::new(position.x, -150.0), Vec2::from);
+        let spacing = Vec2::from(event.group.spacing);
+
+        for i in 0..event.group.count {
+            let offset = spacing * i as f32;
+            let dive_delay = event.dive_delays[i as usize];
+            let movement = Self::formation_movement(pattern.clone(), position + offset, entry + offset, dive_delay);
+            let due = self.tick_count + (event.delay + i * event.group.interval) as usize;
+            self.scheduled.push((due, def.clone(), movement));
+        }
+    }
+
+    fn spawn_scheduled(&mut self) {
+        let (due, later) = std::mem::take(&mut self.scheduled)
+            .into_iter()
+            .partition(|(tick, _, _)| *tick <= self.tick_count);
+        self.scheduled = later;
+        for (_, def, movement) in due {
+            self.spawn_enemy(&def, movement);
+        }
+    }
+
+    //joins the formation at `slot` after flying in from `entry`, with its dives offset by `dive_delay`
+    fn formation_movement(pattern: Pattern, slot: Vec2, entry: Vec2, dive_delay: u32) -> Movement {
+        Movement::new(pattern, slot, Drift::default())
+            .with_delay(dive_delay)
+            .with_entry(entry, 120)
+    }
+
+    fn spawn_enemy(&mut self, def: &EnemyDef, movement: Movement) {
+        self.enemies.push(Enemy::new(
+            self.next_enemy_id, def, movement, &mut self.animation_system, &self.animation_registry, &self.hitboxes,
+        ));
+        self.next_enemy_id += 1;
+    }
+
+    fn handle_player_death(&mut self) {
+        self.status = Some("game over");
+        self.player.alive = false;
+        self.player.health = 0.0;
+        self.explosions.push(Explosion::new(
+            self.player.position, 64.0,
+            &mut self.animation_system, &self.animation_registry,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn simulation() -> Simulation {
+        let data = GameData::load(&Path::new(env!("CARGO_MANIFEST_DIR")).join("resources")).unwrap();
+        Simulation::new(7, &data)
+    }
+
+    #[test]
+    fn steps_headlessly_and_follows_input() {
+        let mut sim = simulation();
+        let start = sim.player.position;
+
+        let right = Input { right: true, ..Input::default() };
+        for _ in 0..100 {
+            sim.step(&right);
+        }
+        let moved_right = sim.player.position;
+        assert!(moved_right.x > start.x, "holding right moved the player from {start} to {moved_right}");
+
+        let left_and_fire = Input { left: true, fire: true, ..Input::default() };
+        let mut fired = false;
+        for _ in 0..200 {
+            sim.step(&left_and_fire);
+            fired |= !sim.bullets.is_empty();
+        }
+        assert!(sim.player.position.x < moved_right.x, "holding left moved the player back");
+        assert!(fired, "holding fire shot bullets");
+        assert_eq!(sim.tick_count, 300);
+        assert!(sim.player.alive);
+    }
+
+    #[test]
+    fn input_survives_packing_into_bits() {
+        let input = Input { up: true, fire: true, bomb: true, ..Input::default() };
+        assert_eq!(Input::from_bits(input.to_bits()), input);
+    }
+}
@@ -2,6 +2,8 @@ use ggez::graphics::Rect;
 use serde::{Serialize, Deserialize};
 use std::collections::HashMap;
 
+pub const SPRITESHEET_FILE: &str = "spaceship_sprites.json";
+
 pub trait SpriteObject {
     fn get_frame(&self, _sprite_system: &SpriteAnimationSystem) -> Option<String> { None }
     fn get_fractional_frame(&self, sprite_system: &SpriteAnimationSystem, sprite_data: &SpriteSheetData) -> Option<Rect> {
@@ -16,33 +18,45 @@ pub trait SpriteObject {
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SpriteAnimationSystem {
-    store: Vec<SpriteAnimationComponent>
+    store: Vec<Option<SpriteAnimationComponent>>,
+    //handles given back with `release`, reused before the store grows
+    free: Vec<usize>,
 }
 impl SpriteAnimationSystem {
     pub fn new() -> SpriteAnimationSystem {
-        SpriteAnimationSystem { store: vec![] }
+        SpriteAnimationSystem { store: vec![], free: vec![] }
     }
     pub fn add_anim(&mut self, anim: SpriteAnimationComponent) -> usize {
-        self.store.push(anim);
-        self.store.len()-1
-    }
-    pub fn add_registered_anim(&mut self, registry_key: String, registry: &SpriteAnimationRegistry) -> Option<usize> {
-        if let Some(anim) = registry.get_anim(registry_key) {
-            Some(self.add_anim(SpriteAnimationComponent::new(anim)))
+        if let Some(handle) = self.free.pop() {
+            self.store[handle] = Some(anim);
+            handle
         } else {
-            None
+            self.store.push(Some(anim));
+            self.store.len()-1
         }
     }
+    //stop animating `anim_handle` and free it up for reuse; the owner must not use it afterwards
+    pub fn release(&mut self, anim_handle: usize) {
+        if let Some(slot) = self.store.get_mut(anim_handle) {
+            if slot.take().is_some() {
+                self.free.push(anim_handle);
+            }
+        }
+    }
+    pub fn add_registered_anim(&mut self, registry_key: String, registry: &SpriteAnimationRegistry) -> Option<usize> {
+        registry.get_anim(registry_key)
+            .map(|anim| self.add_anim(SpriteAnimationComponent::new(anim)))
+    }
     pub fn time_tick(&mut self, tick: f32) {
-        for anim in &mut self.store {
+        for anim in self.store.iter_mut().flatten() {
             anim.time_tick(tick);
         }
     }
     pub fn get_anim(&self, anim_handle: usize) -> Option<&SpriteAnimationComponent> {
-        self.store.get(anim_handle)
+        self.store.get(anim_handle).and_then(Option::as_ref)
     }
     pub fn get_frame(&self, anim_handle: usize) -> Option<&String> {
-        self.store.get(anim_handle).map(|anim| anim.get_frame())
+        self.get_anim(anim_handle).map(|anim| anim.get_frame())
     }
 }
 
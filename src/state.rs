@@ -1,81 +1,57 @@
-use ggez::graphics::{Color, DrawMode, DrawParam, Image, Mesh, Rect, Text, TextFragment, Canvas, InstanceArray};
+use ggez::graphics::{Color, DrawMode, DrawParam, Image, Mesh, MeshBuilder, Rect, Text, TextFragment, Canvas, InstanceArray};
 use ggez::input::keyboard::{KeyCode, KeyInput};
 use ggez::event::EventHandler;
 use ggez::glam::Vec2;
 use ggez::*;
-use rand::Rng;
-use rand::rngs::ThreadRng;
-use rand_distr::StandardNormal;
 use std::collections::HashSet;
 use std::io::Read;
+use std::path::PathBuf;
 use std::str;
 
 use crate::config::*;
-use crate::spritesheet::{SpriteAnimation, SpriteSheetData, SpriteAnimationSystem, SpriteAnimationRegistry, SpriteObject};
-use crate::shooter::{Player, Enemy, Bullet, BulletType, Star, GameObject, Explosion};
-
+use crate::data::GameData;
+use crate::hitbox::Shape;
+use crate::replay::{Replay, ReplayResult};
+use crate::simulation::{Input, Simulation};
+use crate::spritesheet::{SpriteSheetData, SpriteAnimationSystem, SpriteObject, SPRITESHEET_FILE};
+use crate::shooter::GameObject;
+
+/// ggez adapter around `Simulation`: collects keyboard input and renders the game.
 pub struct State {
-    player: Player,
-    bullets: Vec<Bullet>,
-    enemy_bullets: Vec<Bullet>,
-    enemies: Vec<Enemy>,
-    explosions: Vec<Explosion>,
-    stars: Vec<Star>,
+    sim: Simulation,
     keys: HashSet<KeyCode>,
-    rng: ThreadRng,
-    stage: usize,
-    no_attack_timer: usize,
-    tick_count: usize,
-    status: Option<&'static str>,
-    animation_registry: SpriteAnimationRegistry,
-    animation_system: SpriteAnimationSystem,
+    //when set, inputs are read from this replay instead of the keyboard
+    playback: Option<Replay>,
+    //replay being recorded, and where to save it on quit
+    recording: Option<(PathBuf, Replay)>,
+    //real time (in seconds) not yet consumed by a simulation tick
+    accumulator: f32,
     spritesheet_data: SpriteSheetData,
     spritesheet_instances: InstanceArray,
 }
 
 impl State {
-    pub fn new(ctx: &mut Context) -> GameResult<Self> {
+    pub fn new(
+        ctx: &mut Context,
+        seed: u64,
+        data: &GameData,
+        playback: Option<Replay>,
+        record_path: Option<PathBuf>,
+    ) -> GameResult<Self> {
         let mut buffer = Vec::new();
-        let mut spritesheet_data_file = ctx.fs.open("/spaceship_sprites.json")?;
+        let mut spritesheet_data_file = ctx.fs.open(format!("/{SPRITESHEET_FILE}"))?;
         spritesheet_data_file.read_to_end(&mut buffer)?;
         let spritesheet_data: SpriteSheetData =
             serde_json::from_str(str::from_utf8(&buffer).unwrap()).unwrap();
 
-        let mut animation_registry = SpriteAnimationRegistry::new();
-        animation_registry.add_anim(
-            "explosion".to_string(),
-            SpriteAnimation::new(
-                (1..=9).map(|i| format!("Explosion01_Frame_{i:02}")).collect(),
-                1000.0 / 24.0,
-                false,
-            ),
-        );
-        animation_registry.add_anim(
-            "player_turn".to_string(),
-            SpriteAnimation::new(
-                (1..=3).map(|i| format!("PlayerBlue_Frame_{i:02}")).collect(),
-                1000.0 / 12.0,
-                false,
-            ),
-        );
-
         let spritesheet_image = Image::from_path(ctx, "/spaceship_sprites.png")?;
 
         Ok(Self {
-            player: Player::new(),
-            bullets: Vec::new(),
-            enemy_bullets: Vec::new(),
-            enemies: Vec::new(),
-            explosions: Vec::new(),
-            stars: Vec::new(),
+            sim: Simulation::new(seed, data),
             keys: HashSet::with_capacity(6),
-            rng: rand::thread_rng(),
-            stage: 0,
-            no_attack_timer: 0,
-            tick_count: 0,
-            status: None,
-            animation_registry,
-            animation_system: SpriteAnimationSystem::new(),
+            playback,
+            recording: record_path.map(|path| (path, Replay::new(seed))),
+            accumulator: 0.0,
             spritesheet_data,
             spritesheet_instances: InstanceArray::new(ctx, spritesheet_image),
         })
@@ -83,166 +59,18 @@ impl State {
 
     // -- Input ----------------------------------------------------------------
 
-    fn handle_keys(&mut self, ctx: &mut Context) {
-        // Snapshot keys so we can mutate self freely
-        let keys: Vec<KeyCode> = self.keys.iter().copied().collect();
-        for key in keys {
-            match key {
-                KeyCode::Up    => self.player.velocity += Vec2::new(0.0, -1.0),
-                KeyCode::Down  => self.player.velocity += Vec2::new(0.0,  1.0),
-                KeyCode::Left  => self.player.velocity += Vec2::new(-1.0, 0.0),
-                KeyCode::Right => self.player.velocity += Vec2::new( 1.0, 0.0),
-                KeyCode::LShift => {
-                    if self.player.alive && self.player.bullet_spacing == 0 {
-                        self.player.cycle_weapons();
-                        self.player.bullet_spacing += 50;
-                    }
-                }
-                KeyCode::Space => {
-                    if self.player.alive && self.player.bullet_spacing == 0 && self.no_attack_timer == 0 {
-                        self.bullets.extend(self.player.shoot());
-                        self.player.bullet_spacing = self.player.weapon().fire_rate();
-                    }
-                }
-                KeyCode::Escape => ctx.request_quit(),
-                _ => {}
-            }
-        }
-    }
-
-    // -- Physics & Collisions -------------------------------------------------
-
-    fn handle_bullets(&mut self) {
-        let no_attack = self.no_attack_timer > 0;
-
-        // Player bullets vs enemies
-        for bullet in &mut self.bullets {
-            bullet.physics();
-            if no_attack { continue; }
-            for enemy in &mut self.enemies {
-                if bullet.collides_with(enemy) {
-                    bullet.alive = false;
-                    enemy.health -= bullet.damage;
-                    enemy.flash_frames = 5;
-                }
-            }
-        }
-        self.bullets.retain(|b| b.alive && !b.is_off_screen());
-
-        // Enemy bullets vs player
-        let player_vulnerable = self.player.is_vulnerable() && !no_attack;
-        for bullet in &mut self.enemy_bullets {
-            bullet.physics();
-            if player_vulnerable && bullet.collides_with(&self.player) {
-                bullet.alive = false;
-                self.player.take_damage(bullet.damage);
-            }
-        }
-        self.enemy_bullets.retain(|b| b.alive && !b.is_off_screen());
-    }
-
-    fn handle_enemies(&mut self) {
-        let num_enemies = self.enemies.len();
-        let no_attack = self.no_attack_timer > 0;
-
-        for enemy in &mut self.enemies {
-            enemy.physics();
-
-            // Enemy shooting
-            if self.player.alive && !no_attack {
-                let scaled_chance = ENEMY_SHOOT_CHANCE * num_enemies * num_enemies / self.stage;
-                if self.rng.gen_range(0..scaled_chance) == 0 {
-                    let direction = self.player.position - enemy.position;
-                    let dist = direction.length();
-                    let accuracy = 1.0 / dist / num_enemies as f32;
-                    let normal_sample: f32 = self.rng.sample(StandardNormal);
-                    let noise = self.player.size / 2.0 * normal_sample * (1.0 - accuracy) * Vec2::ONE;
-                    let velocity = (direction + noise).normalize() * 3.0;
-                    self.enemy_bullets.push(Bullet::new(
-                        enemy, velocity, Some(Vec2::new(0.0, 20.0)), 10.0, BulletType::Proton,
-                    ));
-                }
-            }
-
-            // Contact damage
-            if !no_attack && self.player.is_vulnerable() && enemy.collides_with(&self.player) {
-                self.player.take_damage(PLAYER_CONTACT_DAMAGE);
-            }
-
-            // Enemy death
-            if enemy.health <= 0.0 {
-                enemy.alive = false;
-                self.player.experience +=
-                    PLAYER_EXP_PER_KILL * 0.7_f32.powf(self.player.weapon().level() as f32);
-            }
-        }
-
-        // Spawn explosions for dead enemies
-        let dead_enemies: Vec<_> = self.enemies.iter()
-            .filter(|e| !e.alive)
-            .map(|e| e.position)
-            .collect();
-        for pos in dead_enemies {
-            self.explosions.push(Explosion::new(
-                pos, 64.0, &mut self.animation_system, &self.animation_registry,
-            ));
-        }
-
-        self.enemies.retain(|e| e.alive);
-    }
-
-    fn handle_background(&mut self) {
-        if self.stars.len() < NUM_STARS && self.rng.gen_range(0.0..1.0_f32) < 0.3 {
-            let x = self.rng.gen_range(0.0..DISPLAY_WIDTH);
-            let normal_sample: f32 = self.rng.sample(StandardNormal);
-            let brightness: f32 = self.rng.gen_range(0.0..1.0);
-            let size = 1.0 + 0.5 * normal_sample.abs();
-            let speed = 0.3 + 0.3 * brightness + 0.3 * normal_sample.abs();
-            self.stars.push(Star::new(
-                Vec2::new(x, 0.0),
-                Vec2::new(0.0, speed),
-                size,
-                brightness,
-            ));
-        }
-
-        for star in &mut self.stars {
-            if star.position.y > DISPLAY_HEIGHT {
-                star.position.y = 0.0;
-            }
-            star.physics();
+    fn input_snapshot(&self) -> Input {
+        Input {
+            up: self.keys.contains(&KeyCode::Up),
+            down: self.keys.contains(&KeyCode::Down),
+            left: self.keys.contains(&KeyCode::Left),
+            right: self.keys.contains(&KeyCode::Right),
+            fire: self.keys.contains(&KeyCode::Space),
+            cycle_weapon: self.keys.contains(&KeyCode::LShift),
+            bomb: self.keys.contains(&KeyCode::B),
         }
     }
 
-    // -- Wave management ------------------------------------------------------
-
-    fn spawn_wave(&mut self) {
-        self.stage += 1;
-        self.no_attack_timer = WAVE_GRACE_PERIOD;
-
-        if self.player.health < PLAYER_MIN_HEALTH_RESTORE {
-            self.player.health = PLAYER_MIN_HEALTH_RESTORE;
-        }
-
-        for x in 0..ENEMIES_PER_ROW {
-            for y in 0..ENEMY_ROWS {
-                self.enemies.push(Enemy::new(
-                    Vec2::new(80.0 + x as f32 * 110.0, 50.0 + y as f32 * 100.0),
-                ));
-            }
-        }
-    }
-
-    fn handle_player_death(&mut self) {
-        self.status = Some("game over");
-        self.player.alive = false;
-        self.player.health = 0.0;
-        self.explosions.push(Explosion::new(
-            self.player.position, 64.0,
-            &mut self.animation_system, &self.animation_registry,
-        ));
-    }
-
     // -- Drawing helpers ------------------------------------------------------
 
     fn draw_hud_bar(
@@ -284,20 +112,33 @@ fn queue_sprite(
     anim_system: &SpriteAnimationSystem,
     sheet_data: &SpriteSheetData,
     obj: &dyn SpriteObject,
-    base_params: DrawParam,
-    dest: Vec2,
-    rotation: Option<f32>,
-    color: Option<Color>,
+    params: DrawParam,
 ) {
     if let Some(src) = obj.get_fractional_frame(anim_system, sheet_data) {
-        let mut params = base_params.src(src).dest(dest);
-        if let Some(r) = rotation {
-            params = params.rotation(r);
-        }
-        if let Some(c) = color {
-            params = params.color(c);
-        }
-        instances.push(params);
+        instances.push(params.src(src));
+    }
+}
+
+//place a trimmed frame so its untrimmed source image starts at `position`, matching the hitboxes built for it;
+//`mirrored` flips it across the source width
+fn source_anchored(
+    params: DrawParam,
+    sheet_data: &SpriteSheetData,
+    frame: Option<String>,
+    position: Vec2,
+    scale: f32,
+    mirrored: bool,
+) -> DrawParam {
+    let Some(sprite) = frame.and_then(|f| sheet_data.frames.get(&f)) else {
+        return params.dest(position).scale(Vec2::splat(scale));
+    };
+    let trim = Vec2::new(sprite.sprite_source_size.x as f32, sprite.sprite_source_size.y as f32);
+    if mirrored {
+        //flipping mirrors around `dest`, so measure the trim from the source's right edge instead
+        let corner = Vec2::new(sprite.source_size.w as f32 - trim.x, trim.y);
+        params.dest(position + corner * scale).scale(Vec2::new(-scale, scale))
+    } else {
+        params.dest(position + trim * scale).scale(Vec2::splat(scale))
     }
 }
 
@@ -307,49 +148,29 @@ fn queue_sprite(
 
 impl EventHandler for State {
     fn update(&mut self, ctx: &mut Context) -> GameResult {
-        self.tick_count += 1;
-
-        if SHOW_FRAMERATE && self.tick_count % 60 == 0 {
-            println!(
-                "FPS: {:.0}, #Bullets: {}",
-                ctx.time.fps(),
-                self.bullets.len() + self.enemy_bullets.len()
-            );
-        }
-
-        self.no_attack_timer = self.no_attack_timer.saturating_sub(1);
+        // Fixed timestep: bank real elapsed time and spend it in whole simulation ticks
+        let tick_length = 1.0 / TICKS_PER_SECOND as f32;
+        self.accumulator = (self.accumulator + ctx.time.delta().as_secs_f32()).min(MAX_FRAME_TIME);
 
-        self.handle_keys(ctx);
-        self.handle_bullets();
-        self.handle_enemies();
-        self.handle_background();
+        while self.accumulator >= tick_length {
+            self.accumulator -= tick_length;
 
-        // Expire finished explosions
-        for exp in &mut self.explosions {
-            if exp.poll_animation_finished(&self.animation_system) {
-                exp.finished = true;
+            let input = match &self.playback {
+                Some(replay) => replay.input_at(self.sim.tick_count).unwrap_or_default(),
+                None => self.input_snapshot(),
+            };
+            if let Some((_, replay)) = &mut self.recording {
+                replay.record(input);
+            }
+            self.sim.step(&input);
+
+            if SHOW_FRAMERATE && self.sim.tick_count.is_multiple_of(60) {
+                println!(
+                    "FPS: {:.0}, #Bullets: {}",
+                    ctx.time.fps(),
+                    self.sim.bullets.len() + self.sim.enemy_bullets.len()
+                );
             }
-        }
-        self.explosions.retain(|exp| !exp.finished);
-
-        // Advance animations
-        self.animation_system.time_tick(ctx.time.delta().as_millis() as f32);
-
-        // Player physics & leveling
-        self.player.physics();
-        if self.player.experience >= EXP_TO_LEVEL {
-            self.player.experience = 0.0;
-            self.player.weapon_mut().level_up();
-        }
-
-        // Wave progression
-        if self.player.alive && self.enemies.is_empty() {
-            self.spawn_wave();
-        }
-
-        // Death check
-        if self.player.alive && self.player.health <= 0.0 {
-            self.handle_player_death();
         }
 
         Ok(())
@@ -357,79 +178,98 @@ impl EventHandler for State {
 
     fn draw(&mut self, ctx: &mut Context) -> GameResult {
         let mut canvas = Canvas::from_frame(ctx, Color::BLACK);
+        let sim = &self.sim;
+        // How far we are between the last tick and the next one, for interpolating positions
+        let alpha = self.accumulator * TICKS_PER_SECOND as f32;
 
         // In ggez 0.9, offset affects both positioning and rotation origin.
         // Use offset(0,0) for all sprites so dest = top-left, matching ggez 0.5 SpriteBatch behavior.
         let base_params = DrawParam::default();
 
         // -- Background stars -------------------------------------------------
-        for star in &self.stars {
+        for star in &sim.stars {
             let dim = star.brightness * 0.8;
             let mesh = Mesh::new_circle(
-                ctx, DrawMode::fill(), star.position, star.size, 1.0,
+                ctx, DrawMode::fill(), star.interpolated_position(alpha), star.size, 1.0,
                 Color::new(dim, dim, dim, 1.0),
             )?;
             canvas.draw(&mesh, DrawParam::default());
         }
 
+        // -- Bomb blast -------------------------------------------------------
+        if let Some(blast) = &sim.blast {
+            let strength = blast.strength();
+            let mesh = Mesh::new_circle(
+                ctx, DrawMode::fill(), blast.center, blast.radius * (1.0 - 0.5 * strength), 1.0,
+                Color::new(1.0, 0.9, 0.6, 0.4 * strength),
+            )?;
+            canvas.draw(&mesh, DrawParam::default());
+        }
+
         // -- Sprites ----------------------------------------------------------
 
-        // Player bullets
-        for bullet in &self.bullets {
+        // Player and enemy bullets
+        for bullet in sim.bullets.iter().chain(&sim.enemy_bullets) {
             queue_sprite(
-                &mut self.spritesheet_instances, &self.animation_system, &self.spritesheet_data,
-                bullet, base_params, bullet.position(), Some(bullet.angle), None,
+                &mut self.spritesheet_instances, &sim.animation_system, &self.spritesheet_data,
+                bullet, base_params.dest(bullet.interpolated_position(alpha)).rotation(bullet.angle),
             );
         }
 
-        // Enemy bullets
-        for bullet in &self.enemy_bullets {
+        // Enemies
+        for enemy in &sim.enemies {
+            let mut params = source_anchored(
+                base_params, &self.spritesheet_data, enemy.get_frame(&sim.animation_system),
+                enemy.interpolated_position(alpha), enemy.sprite_scale(), false,
+            ).color(Color::from(enemy.def.tint));
+            if enemy.flash_frames > 0 {
+                params = params.color(Color::new(3.0, 0.8, 0.8, 1.0));
+            }
             queue_sprite(
-                &mut self.spritesheet_instances, &self.animation_system, &self.spritesheet_data,
-                bullet, base_params, bullet.position(), Some(bullet.angle), None,
+                &mut self.spritesheet_instances, &sim.animation_system, &self.spritesheet_data,
+                enemy, params,
             );
         }
 
-        // Enemies
-        for enemy in &self.enemies {
-            let color = if enemy.flash_frames > 0 {
-                Some(Color::new(3.0, 0.8, 0.8, 1.0))
-            } else {
-                None
-            };
+        // Beam, stretched from the player's (interpolated) position up to where it was cut off
+        if let Some(beam) = &sim.beam {
+            let sprite_height = self.spritesheet_data.frames.get("Laser_Large")
+                .map_or(1.0, |sprite| sprite.frame.h as f32);
+            //the beam was cast before the player moved this tick, i.e. from its previous position
+            let origin = sim.player.interpolated_position(alpha) + beam.origin - sim.player.previous_position();
+            let params = base_params
+                .dest(origin - Vec2::new(beam.width / 2.0, beam.length))
+                .scale(Vec2::new(1.0, beam.length / sprite_height));
             queue_sprite(
-                &mut self.spritesheet_instances, &self.animation_system, &self.spritesheet_data,
-                enemy, base_params, enemy.position(), None, color,
+                &mut self.spritesheet_instances, &sim.animation_system, &self.spritesheet_data,
+                beam, params,
             );
         }
 
         // Explosions
-        for exp in &self.explosions {
+        for exp in &sim.explosions {
             queue_sprite(
-                &mut self.spritesheet_instances, &self.animation_system, &self.spritesheet_data,
-                exp, base_params, exp.position(), None, None,
+                &mut self.spritesheet_instances, &sim.animation_system, &self.spritesheet_data,
+                exp, base_params.dest(exp.interpolated_position(alpha)),
             );
         }
 
         // Player
-        if self.player.alive {
-            let flash_period = PLAYER_INVINCIBILITY_FRAMES as usize / 10;
-            let flashing = (self.player.invincibility_frames > 0 || self.no_attack_timer > 0)
-                && self.tick_count / flash_period % 2 == 0;
-            let color = if flashing {
-                Some(Color::new(1.0, 1.0, 1.0, 0.1))
-            } else {
-                None
-            };
-            let player_pos = self.player.position();
-            let player_frame = self.player.get_fractional_frame(&self.animation_system, &self.spritesheet_data);
-            if let Some(src) = player_frame {
-                let mut params = base_params.src(src).dest(player_pos);
-                if let Some(c) = color {
-                    params = params.color(c);
-                }
-                self.spritesheet_instances.push(params);
+        if sim.player.alive {
+            let flash_period = (sim.config.player_invincibility_frames as usize / 10).max(1);
+            let flashing = (sim.player.invincibility_frames > 0 || sim.no_attack_timer > 0)
+                && (sim.tick_count / flash_period).is_multiple_of(2);
+            let mut params = source_anchored(
+                base_params, &self.spritesheet_data, sim.player.get_frame(&sim.animation_system),
+                sim.player.interpolated_position(alpha), 1.0, sim.player.is_mirrored(),
+            );
+            if flashing {
+                params = params.color(Color::new(1.0, 1.0, 1.0, 0.1));
             }
+            queue_sprite(
+                &mut self.spritesheet_instances, &sim.animation_system, &self.spritesheet_data,
+                &sim.player, params,
+            );
         }
 
         canvas.draw(&self.spritesheet_instances, DrawParam::default());
@@ -440,51 +280,108 @@ impl EventHandler for State {
             let hitbox_color = Color::from(HITBOX_COLOR);
             let mut draw_hitboxes = |tree: &crate::hitbox::HitboxTree| -> GameResult {
                 for hb in tree.bfs_iter() {
-                    let rect = Rect::new(hb.point.x, hb.point.y, hb.size.x, hb.size.y);
-                    let mesh = Mesh::new_rectangle(ctx, DrawMode::fill(), rect, hitbox_color)?;
+                    let mesh = match &hb.shape {
+                        Shape::Rect => {
+                            let rect = Rect::new(hb.point.x, hb.point.y, hb.size.x, hb.size.y);
+                            Mesh::new_rectangle(ctx, DrawMode::fill(), rect, hitbox_color)?
+                        }
+                        Shape::Circle { center, radius } => {
+                            Mesh::new_circle(ctx, DrawMode::fill(), *center, *radius, 0.5, hitbox_color)?
+                        }
+                        Shape::Capsule { start, end, radius } => {
+                            let mut builder = MeshBuilder::new();
+                            builder.line(&[*start, *end], 2.0 * radius, hitbox_color)?;
+                            for end in [start, end] {
+                                builder.circle(DrawMode::fill(), *end, *radius, 0.5, hitbox_color)?;
+                            }
+                            Mesh::from_data(ctx, builder.build())
+                        }
+                        Shape::OrientedRect { .. } | Shape::Polygon { .. } => {
+                            Mesh::new_polygon(ctx, DrawMode::fill(), &hb.outline(), hitbox_color)?
+                        }
+                    };
                     canvas.draw(&mesh, DrawParam::default());
                 }
                 Ok(())
             };
-            for b in &self.bullets { draw_hitboxes(&b.hitbox_tree)?; }
-            for b in &self.enemy_bullets { draw_hitboxes(&b.hitbox_tree)?; }
-            for e in &self.enemies { draw_hitboxes(&e.hitbox_tree)?; }
-            if self.player.alive { draw_hitboxes(&self.player.hitbox_tree)?; }
+            for b in &sim.bullets { draw_hitboxes(&b.hitbox_tree)?; }
+            for b in &sim.enemy_bullets { draw_hitboxes(&b.hitbox_tree)?; }
+            for e in &sim.enemies { draw_hitboxes(&e.hitbox_tree)?; }
+            if sim.player.alive { draw_hitboxes(&sim.player.hitbox_tree)?; }
         }
 
         // -- HUD --------------------------------------------------------------
         let hud_y = 23.0 * DISPLAY_HEIGHT / 24.0 - 10.0;
         let health_pos = Vec2::new(10.0, hud_y);
-        let exp_pos = Vec2::new(9.0 * DISPLAY_WIDTH / 12.0 - 10.0, hud_y);
 
         Self::draw_hud_bar(
             &mut canvas, ctx, health_pos,
-            self.player.health / PLAYER_MAX_HEALTH,
+            sim.player.health / sim.config.player_max_health,
             Color::RED,
-            &format!("health: {}/{}", self.player.health, PLAYER_MAX_HEALTH),
-        )?;
-        Self::draw_hud_bar(
-            &mut canvas, ctx, exp_pos,
-            self.player.experience / EXP_TO_LEVEL,
-            Color::GREEN,
-            &self.player.weapon().info(),
+            &format!("health: {}/{}", sim.player.health, sim.config.player_max_health),
         )?;
+        // One experience bar per weapon, stacked up from the bottom right; the selected one is brightest.
+        // Weapons that build heat get a heat bar to the left of their experience bar, labelled with how
+        // fast they fire in a burst and how fast they can keep firing without overheating
+        let bar_spacing = DISPLAY_HEIGHT / 24.0 + 6.0;
+        for (i, weapon) in sim.player.weapons().iter().enumerate().rev() {
+            let slots_from_bottom = (sim.player.weapons().len() - 1 - i) as f32;
+            let exp_pos = Vec2::new(9.0 * DISPLAY_WIDTH / 12.0 - 10.0, hud_y - slots_from_bottom * bar_spacing);
+            let selected = i == sim.player.weapon_idx();
+            Self::draw_hud_bar(
+                &mut canvas, ctx, exp_pos,
+                weapon.experience() / sim.config.exp_to_level,
+                if selected { Color::GREEN } else { Color::new(0.0, 0.5, 0.0, 1.0) },
+                &if selected { format!("> {}", weapon.info()) } else { weapon.info() },
+            )?;
+            if let Some(heat) = weapon.heat() {
+                let heat_pos = exp_pos - Vec2::new(3.0 * DISPLAY_WIDTH / 12.0 + 10.0, 0.0);
+                let (burst, sustained) = weapon.fire_rate().per_second();
+                Self::draw_hud_bar(
+                    &mut canvas, ctx, heat_pos,
+                    heat.fraction(),
+                    if heat.overheated { Color::RED } else { Color::new(1.0, 0.6, 0.0, 1.0) },
+                    &if heat.overheated {
+                        "OVERHEATED".to_string()
+                    } else {
+                        format!("heat ( burst {burst:.1}/s, held {sustained:.1}/s )")
+                    },
+                )?;
+            }
+        }
+
+        // Boss health, top center, counting its remaining parts
+        if let Some(boss) = sim.enemies.iter().find(|e| e.def.boss) {
+            let bar_width = 3.0 * DISPLAY_WIDTH / 12.0;
+            Self::draw_hud_bar(
+                &mut canvas, ctx, Vec2::new((DISPLAY_WIDTH - bar_width) / 2.0, 10.0),
+                boss.health_fraction(),
+                Color::new(0.8, 0.3, 1.0, 1.0),
+                &boss.def.name,
+            )?;
+        }
+
+        // Bomb stock, just above the health bar
+        canvas.draw(
+            &Text::new(TextFragment::new(format!("bombs: {}/{}", sim.player.bombs, sim.config.player_bombs))),
+            DrawParam::default().dest(health_pos - Vec2::new(0.0, 20.0)),
+        );
 
         // Stage counter / wave timer
-        let stage_label = if self.no_attack_timer > 0 {
-            format!("timer: {}", self.no_attack_timer)
+        let stage_label = if sim.no_attack_timer > 0 {
+            format!("timer: {}", sim.no_attack_timer)
         } else {
-            format!("stage: {}", self.stage)
+            format!("stage: {}  score: {}", sim.stage, sim.score)
         };
         canvas.draw(
             &Text::new(TextFragment::new(stage_label)),
             DrawParam::default().dest(Vec2::new(20.0, 10.0)),
         );
 
-        // Game-over text
-        if let Some(status) = self.status {
+        // Game-over text, with the seed so the run can be reproduced
+        if let Some(status) = sim.status {
             canvas.draw(
-                &Text::new(TextFragment::new(status)),
+                &Text::new(TextFragment::new(format!("{status} (seed: {})", sim.seed))),
                 DrawParam::default().dest(Vec2::new(DISPLAY_WIDTH / 2.0 - 50.0, DISPLAY_HEIGHT / 2.0)),
             );
         }
@@ -493,6 +390,15 @@ impl EventHandler for State {
         Ok(())
     }
 
+    fn quit_event(&mut self, _ctx: &mut Context) -> Result<bool, GameError> {
+        if let Some((path, replay)) = &mut self.recording {
+            replay.expected = Some(ReplayResult::of(&self.sim));
+            replay.save(path)?;
+            println!("saved replay to {}", path.display());
+        }
+        Ok(false)
+    }
+
     fn key_up_event(&mut self, _ctx: &mut Context, input: KeyInput) -> Result<(), GameError> {
         if let Some(keycode) = input.keycode {
             self.keys.remove(&keycode);
@@ -500,8 +406,11 @@ impl EventHandler for State {
         Ok(())
     }
 
-    fn key_down_event(&mut self, _ctx: &mut Context, input: KeyInput, _repeat: bool) -> Result<(), GameError> {
+    fn key_down_event(&mut self, ctx: &mut Context, input: KeyInput, _repeat: bool) -> Result<(), GameError> {
         if let Some(keycode) = input.keycode {
+            if keycode == KeyCode::Escape {
+                ctx.request_quit();
+            }
             self.keys.insert(keycode);
         }
         Ok(())
@@ -0,0 +1,364 @@
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use serde::Deserialize;
+
+use crate::bullet_pattern::BulletPattern;
+use crate::config::{Config, WORLD_HEIGHT, WORLD_WIDTH};
+use crate::enemy::{EnemyDef, EnemyFire};
+use crate::movement::Pattern;
+use crate::shooter::{BulletSize, BulletType};
+
+pub const WAVES_FILE: &str = "waves.json";
+
+//dive delays are drawn from 0 up to this many ticks
+const DIVE_STAGGER: u32 = 900;
+
+/// An authored stage from `resources/waves.json`: enemies to spawn, each at its own time.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WaveScript {
+    pub events: Vec<SpawnEvent>,
+}
+
+impl WaveScript {
+    //give every enemy without a dive delay a random one
+    pub fn stagger_dives(&mut self, rng: &mut impl Rng) {
+        for event in self.events.iter_mut().filter(|event| event.dive_delays.is_empty()) {
+            event.dive_delays = (0..event.group.count).map(|_| rng.gen_range(0..DIVE_STAGGER)).collect();
+        }
+    }
+}
+
+/// Spawns a group of one enemy type into the formation.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpawnEvent {
+    //name of an entry in `enemies.json`
+    pub enemy: String,
+    //ticks after the stage starts
+    #[serde(default)]
+    pub delay: u32,
+    //formation slot of the first enemy in the group
+    pub position: [f32; 2],
+    //where the group flies in from; straight above its slot if not given
+    #[serde(default)]
+    pub entry: Option<[f32; 2]>,
+    //replaces the enemy type's own movement pattern
+    #[serde(default)]
+    pub movement: Option<Pattern>,
+    //replaces the enemy type's own weapon
+    #[serde(default)]
+    pub fire: Option<EnemyFire>,
+    //multiplies the health of the enemy and its parts
+    #[serde(default = "one")]
+    pub health: f32,
+    #[serde(default)]
+    pub group: Group,
+    //ticks each member of the group is offset by when timing its dives, so a formation doesn't
+    //attack all at once; drawn from the stage's own random numbers when not given
+    #[serde(default)]
+    pub dive_delays: Vec<u32>,
+}
+
+fn one() -> f32 { 1.0 }
+
+/// A line of identical enemies spawned by one event.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Group {
+    pub count: u32,
+    //offset from one member's slot (and entry point) to the next
+    pub spacing: [f32; 2],
+    //ticks between one member spawning and the next, so a group can stream in
+    #[serde(default)]
+    pub interval: u32,
+}
+
+impl Default for Group {
+    fn default() -> Self {
+        Self { count: 1, spacing: [0.0, 0.0], interval: 0 }
+    }
+}
+
+/// The random numbers of one stage, which only depend on `stage` and `seed`, independent of
+/// anything that happened earlier in the run.
+pub fn stage_rng(stage: usize, seed: u64) -> StdRng {
+    StdRng::seed_from_u64(seed ^ (stage as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15))
+}
+
+// ================================================================================================
+// -- Generated waves -----------------------------------------------------------------------------
+// ================================================================================================
+
+//formation rows start this far down the screen, and keep to the top half of it
+const ROWS_TOP: f32 = 50.0;
+const ROWS_BOTTOM: f32 = WORLD_HEIGHT / 2.0;
+const ROW_GAP: f32 = 15.0;
+//preferred distance between neighbours in a row, and the margin kept from the screen edges
+const SLOT_SPACING: f32 = 110.0;
+const SIDE_MARGIN: f32 = 60.0;
+//how far apart the rows of a stage start flying in, in ticks
+const ROW_DELAY: u32 = 60;
+//how much lower each enemy down the arms of a V is than the one before
+const VEE_DROP: f32 = 20.0;
+//fewest enemies a row is worth arming with a stronger weapon
+const MIN_ARMED_ROW: u32 = 3;
+//bosses hold this far down the screen, after flying in from this far above it
+const BOSS_TOP: f32 = 60.0;
+const BOSS_ENTRY_HEIGHT: f32 = 400.0;
+
+/// How the enemies of a generated row are laid out and fly in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Formation {
+    //a straight line dropping in from above
+    Line,
+    //a straight line streaming in one by one from the side of the screen
+    Stream,
+    //a V pointing down, its arms flying in from above
+    Vee,
+}
+
+impl Formation {
+    const ALL: [Formation; 3] = [Formation::Line, Formation::Stream, Formation::Vee];
+
+    //difficulty points spent on a row for flying in this way
+    fn cost(self) -> f32 {
+        match self {
+            Formation::Line => 0.0,
+            Formation::Vee => 2.0,
+            Formation::Stream => 3.0,
+        }
+    }
+
+    //height of the V below its tip, or nothing for straight lines
+    fn depth(self, count: u32) -> f32 {
+        match self {
+            Formation::Vee => (count / 2) as f32 * VEE_DROP,
+            _ => 0.0,
+        }
+    }
+}
+
+//weapons a generated row can be armed with instead of its own, and their cost per enemy
+const FIRE_UPGRADES: [(f32, EnemyFire); 4] = [
+    (1.0, EnemyFire {
+        bullet_type: BulletType::Proton, bullet_size: BulletSize::Small, speed: 3.0, damage: 8.0,
+        pattern: Some(BulletPattern::AimedFan { count: 3, spread: 0.5, period: 150 }),
+        rate: 1.0, spread: 1.0,
+    }),
+    (1.5, EnemyFire {
+        bullet_type: BulletType::Plasma, bullet_size: BulletSize::Medium, speed: 2.0, damage: 10.0,
+        pattern: Some(BulletPattern::Radial { count: 8, period: 200 }),
+        rate: 1.0, spread: 1.0,
+    }),
+    (2.0, EnemyFire {
+        bullet_type: BulletType::Laser, bullet_size: BulletSize::Small, speed: 5.0, damage: 10.0,
+        pattern: Some(BulletPattern::DelayedBurst { period: 400, delay: 60, volleys: 3, gap: 8 }),
+        rate: 1.0, spread: 1.0,
+    }),
+    (3.0, EnemyFire {
+        bullet_type: BulletType::Plasma, bullet_size: BulletSize::Small, speed: 2.0, damage: 8.0,
+        pattern: Some(BulletPattern::Spiral { arms: 2, period: 20, spin: 0.35 }),
+        rate: 1.0, spread: 1.0,
+    }),
+];
+
+/// Builds the wave for a stage past the authored ones. Every few stages an unlocked boss flies
+/// in on its own. Otherwise the stage gets a difficulty budget that grows with the stage number,
+/// and every row spends its share of it on an enemy type, a formation, possibly a stronger
+/// weapon, and as many enemies as it can then afford.
+/// The result only depends on `stage` and `seed`, so a run always sees the same waves.
+pub fn generate_wave(stage: usize, seed: u64, enemies: &[EnemyDef], config: &Config) -> WaveScript {
+    let mut rng = stage_rng(stage, seed);
+    let health = config.enemy_health_scale(stage);
+
+    let bosses: Vec<&EnemyDef> = enemies.iter().filter(|def| def.boss && def.from_stage <= stage).collect();
+    if stage.is_multiple_of(config.boss_every) && !bosses.is_empty() {
+        let boss = bosses[rng.gen_range(0..bosses.len())];
+        let x = (WORLD_WIDTH - boss.size) / 2.0;
+        let event = SpawnEvent {
+            enemy: boss.name.clone(),
+            delay: 0,
+            position: [x, BOSS_TOP],
+            entry: Some([x, BOSS_TOP - BOSS_ENTRY_HEIGHT]),
+            movement: None,
+            fire: None,
+            health,
+            group: Group::default(),
+            dive_delays: vec![0],
+        };
+        return WaveScript { events: vec![event] };
+    }
+
+    let mut budget = config.wave_budget + config.wave_budget_per_stage * stage as f32;
+    let available: Vec<&EnemyDef> = enemies
+        .iter()
+        .filter(|def| !def.boss && def.from_stage <= stage)
+        .collect();
+
+    let mut events = Vec::new();
+    let mut top = ROWS_TOP;
+    for row in 0..config.enemy_rows {
+        //rows share what is left evenly, so an unspent remainder carries over to the next one
+        let share = budget / (config.enemy_rows - row) as f32;
+
+        //the cheapest enemy type is always affordable, so every stage gets at least one row
+        let affordable: Vec<&EnemyDef> = available.iter().copied().filter(|def| def.cost <= share).collect();
+        let def = match affordable.len() {
+            0 if row == 0 => *available.iter().min_by(|a, b| a.cost.total_cmp(&b.cost)).unwrap(),
+            0 => break,
+            n => affordable[rng.gen_range(0..n)],
+        };
+
+        let formations: Vec<Formation> = Formation::ALL
+            .into_iter()
+            .filter(|formation| formation.cost() + def.cost <= share)
+            .collect();
+        let formation = match formations.len() {
+            0 => Formation::Line,
+            n => formations[rng.gen_range(0..n)],
+        };
+        //a stronger weapon makes every enemy in the row cost more, so it is traded against their number
+        let upgrades: Vec<Option<&(f32, EnemyFire)>> = std::iter::once(None)
+            .chain(
+                FIRE_UPGRADES
+                    .iter()
+                    .filter(|(cost, _)| formation.cost() + (def.cost + cost) * MIN_ARMED_ROW as f32 <= share)
+                    .map(Some),
+            )
+            .collect();
+        let upgrade = upgrades[rng.gen_range(0..upgrades.len())];
+        let unit_cost = def.cost + upgrade.map_or(0.0, |(cost, _)| *cost);
+        let count = (((share - formation.cost()) / unit_cost) as u32).clamp(1, config.enemies_per_row as u32);
+        let fire = upgrade.map(|(_, fire)| fire.clone());
+
+        //stop once the rows reach the middle of the screen, leaving the rest to the player
+        let bottom = top + formation.depth(count) + def.size;
+        if row > 0 && bottom > ROWS_BOTTOM {
+            break;
+        }
+        budget -= formation.cost() + unit_cost * count as f32;
+
+        let event = SpawnEvent {
+            enemy: def.name.clone(),
+            delay: row as u32 * ROW_DELAY,
+            position: [0.0, top],
+            entry: None,
+            movement: None,
+            fire,
+            health,
+            group: Group { count, spacing: [0.0, 0.0], interval: 0 },
+            dive_delays: Vec::new(),
+        };
+        events.extend(lay_out(event, formation, def.size, rng.gen_bool(0.5)));
+        top = bottom + ROW_GAP;
+    }
+    let mut script = WaveScript { events };
+    script.stagger_dives(&mut rng);
+    script
+}
+
+//place a row of `event.group.count` enemies centered on the screen at height `event.position[1]`.
+//Streams come in from the left, or from the right if `flip` is set
+fn lay_out(event: SpawnEvent, formation: Formation, size: f32, flip: bool) -> Vec<SpawnEvent> {
+    let count = event.group.count;
+    let y = event.position[1];
+    //squeeze the row together if it would not fit between the margins
+    let room = WORLD_WIDTH - 2.0 * SIDE_MARGIN - size;
+    let spacing = match count {
+        1 => 0.0,
+        n => SLOT_SPACING.min(room / (n - 1) as f32),
+    };
+    let left = (WORLD_WIDTH - size - spacing * (count - 1) as f32) / 2.0;
+    let right = left + spacing * (count - 1) as f32;
+
+    match formation {
+        Formation::Line => vec![SpawnEvent {
+            position: [left, y],
+            group: Group { count, spacing: [spacing, 0.0], interval: 0 },
+            ..event
+        }],
+        Formation::Stream => {
+            //the entry points move along with the slots, so start the whole row past the edge
+            let (start, step, entry_x) = if flip {
+                (right, -spacing, WORLD_WIDTH + SIDE_MARGIN + spacing * (count - 1) as f32)
+            } else {
+                (left, spacing, -size - SIDE_MARGIN - spacing * (count - 1) as f32)
+            };
+            vec![SpawnEvent {
+                position: [start, y],
+                entry: Some([entry_x, y + 50.0]),
+                group: Group { count, spacing: [step, 0.0], interval: 15 },
+                ..event
+            }]
+        }
+        Formation::Vee => {
+            //the tip and the left arm fly in first, then the right arm
+            let left_count = count.div_ceil(2);
+            let tip = left + spacing * (left_count - 1) as f32;
+            let left_arm = SpawnEvent {
+                position: [tip, y],
+                group: Group { count: left_count, spacing: [-spacing, VEE_DROP], interval: 0 },
+                ..event.clone()
+            };
+            let right_arm = SpawnEvent {
+                delay: event.delay + 20,
+                position: [tip + spacing, y + VEE_DROP],
+                group: Group { count: count - left_count, spacing: [spacing, VEE_DROP], interval: 0 },
+                ..event
+            };
+            [left_arm, right_arm].into_iter().filter(|arm| arm.group.count > 0).collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::GameData;
+    use std::collections::HashSet;
+    use std::path::Path;
+
+    fn data() -> GameData {
+        GameData::load(&Path::new(env!("CARGO_MANIFEST_DIR")).join("resources")).unwrap()
+    }
+
+    fn dive_delays(script: &WaveScript) -> Vec<u32> {
+        script.events.iter().flat_map(|event| event.dive_delays.iter().copied()).collect()
+    }
+
+    #[test]
+    fn same_stage_and_seed_give_the_same_wave() {
+        let data = data();
+        for stage in [7, 10, 12] {
+            let first = generate_wave(stage, 42, &data.enemies, &data.config);
+            assert_eq!(first, generate_wave(stage, 42, &data.enemies, &data.config), "stage {stage}");
+        }
+    }
+
+    #[test]
+    fn other_seeds_stagger_dives_differently() {
+        let data = data();
+        let stage: usize = 7;
+        assert!(!stage.is_multiple_of(data.config.boss_every));
+        let first = dive_delays(&generate_wave(stage, 1, &data.enemies, &data.config));
+        assert!(!first.is_empty());
+        assert_ne!(first, dive_delays(&generate_wave(stage, 2, &data.enemies, &data.config)));
+    }
+
+    #[test]
+    fn seed_picks_the_boss() {
+        let data = data();
+        //a second boss, so there is something to pick between
+        let mut enemies = data.enemies.clone();
+        let mut twin = enemies.iter().find(|def| def.boss).unwrap().clone();
+        twin.name = "Twin".to_string();
+        enemies.push(twin);
+
+        let stage = data.config.boss_every * 2;
+        let picks: HashSet<String> = (0..16)
+            .map(|seed| generate_wave(stage, seed, &enemies, &data.config).events[0].enemy.clone())
+            .collect();
+        assert_eq!(picks.len(), 2, "picked {picks:?}");
+    }
+}
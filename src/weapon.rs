@@ -1,13 +1,39 @@
 use ggez::glam::Vec2;
+use serde::Deserialize;
 
-use crate::shooter::{Bullet, BulletType, GameObject};
+use crate::config::TICKS_PER_SECOND;
+use crate::shooter::{Beam, Bullet, BulletSize, BulletType, GameObject};
+
+pub const WEAPONS_FILE: &str = "weapons.json";
+
+/// How holding the fire key turns into shots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FireMode {
+    //fires a volley whenever the cooldown allows while the key is held
+    Burst,
+    //builds up charge while the key is held and fires once when it is released
+    Charge,
+    //projects a continuous `Beam` for as long as the key is held
+    Beam,
+}
 
 pub trait WeaponBehavior {
+    fn core(&self) -> &WeaponCore;
+    fn core_mut(&mut self) -> &mut WeaponCore;
     fn fire(&self, shooter: &dyn GameObject) -> Vec<Bullet>;
-    fn fire_rate(&self) -> u32;
-    fn level(&self) -> u32;
-    fn name(&self) -> &'static str;
-    fn level_up(&mut self);
+
+    fn fire_rate(&self) -> FireRate { self.core().fire_rate() }
+    fn level(&self) -> u32 { self.core().level }
+    fn name(&self) -> &str { &self.core().def.name }
+
+    fn fire_mode(&self) -> FireMode { FireMode::Burst }
+    //called every tick the fire key is held on a `FireMode::Charge` weapon
+    fn charge(&mut self) { }
+    fn charge_fraction(&self) -> f32 { 0.0 }
+    //called after every shot
+    fn discharge(&mut self) { }
+    //called every tick the fire key is held on a `FireMode::Beam` weapon
+    fn beam(&self, _shooter: &dyn GameObject) -> Option<Beam> { None }
 
     fn info(&self) -> String {
         format!("{} ( level: {} )", self.name(), self.level())
@@ -16,140 +42,462 @@ pub trait WeaponBehavior {
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Weapon {
-    MachineGun(MachineGun),
-    WideGun(WideGun),
+    Gun(Gun),
+    Plasma(PlasmaCannon),
+    Homing(MissileLauncher),
+    Beam(BeamLaser),
 }
 
 impl Weapon {
+    pub fn from_def(def: &WeaponDef) -> Weapon {
+        match &def.kind {
+            WeaponKind::Gun { pattern } => Weapon::Gun(Gun::new(def, pattern.clone())),
+            WeaponKind::Plasma { ticks_per_size } => Weapon::Plasma(PlasmaCannon::new(def, *ticks_per_size)),
+            WeaponKind::Homing { turn_rate, launch_spread } => {
+                Weapon::Homing(MissileLauncher::new(def, *turn_rate, *launch_spread))
+            }
+            WeaponKind::Beam { range, width } => Weapon::Beam(BeamLaser::new(def, *range, *width)),
+        }
+    }
     fn inner(&self) -> &dyn WeaponBehavior {
         match self {
-            Weapon::MachineGun(w) => w,
-            Weapon::WideGun(w) => w,
+            Weapon::Gun(w) => w,
+            Weapon::Plasma(w) => w,
+            Weapon::Homing(w) => w,
+            Weapon::Beam(w) => w,
         }
     }
     fn inner_mut(&mut self) -> &mut dyn WeaponBehavior {
         match self {
-            Weapon::MachineGun(w) => w,
-            Weapon::WideGun(w) => w,
+            Weapon::Gun(w) => w,
+            Weapon::Plasma(w) => w,
+            Weapon::Homing(w) => w,
+            Weapon::Beam(w) => w,
         }
     }
     pub fn fire(&self, shooter: &dyn GameObject) -> Vec<Bullet> { self.inner().fire(shooter) }
-    pub fn fire_rate(&self) -> u32 { self.inner().fire_rate() }
-    pub fn level(&self) -> u32 { self.inner().level() }
+    pub fn fire_rate(&self) -> FireRate { self.inner().fire_rate() }
     pub fn info(&self) -> String { self.inner().info() }
-    pub fn level_up(&mut self) { self.inner_mut().level_up(); }
+    pub fn experience(&self) -> f32 { self.inner().core().experience }
+    pub fn heat(&self) -> Option<&Heat> { self.inner().core().heat.as_ref() }
+    pub fn is_overheated(&self) -> bool { self.heat().is_some_and(|heat| heat.overheated) }
+    pub fn fire_mode(&self) -> FireMode { self.inner().fire_mode() }
+    pub fn charge(&mut self) { self.inner_mut().charge(); }
+    pub fn charge_fraction(&self) -> f32 { self.inner().charge_fraction() }
+    pub fn beam(&self, shooter: &dyn GameObject) -> Option<Beam> { self.inner().beam(shooter) }
+
+    //called after every volley, or every tick a beam is on
+    pub fn discharge(&mut self) {
+        let weapon = self.inner_mut();
+        weapon.discharge();
+        if let Some(heat) = &mut weapon.core_mut().heat {
+            heat.add_shot();
+        }
+    }
+
+    //called every tick, whether or not this weapon is selected
+    pub fn cool(&mut self) {
+        if let Some(heat) = &mut self.inner_mut().core_mut().heat {
+            heat.cool();
+        }
+    }
+
+    //credit experience for a kill, scaled down by level; a full bar levels the weapon up
+    pub fn gain_experience(&mut self, amount: f32, exp_to_level: f32) {
+        let core = self.inner_mut().core_mut();
+        core.experience += amount * 0.7_f32.powf(core.level as f32);
+        if core.experience >= exp_to_level {
+            core.experience = 0.0;
+            core.level_up();
+        }
+    }
+}
+
+/// Ticks between shots: `burst` is the cooldown between consecutive shots, `sustained` is
+/// the average spacing that can be held indefinitely without overheating.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FireRate {
+    pub burst: u32,
+    pub sustained: u32,
 }
 
-// -- MachineGun ---------------------------------------------------------------
+impl FireRate {
+    //shots per second at either spacing
+    pub fn per_second(self) -> (f32, f32) {
+        let per_second = |ticks: u32| TICKS_PER_SECOND as f32 / ticks.max(1) as f32;
+        (per_second(self.burst), per_second(self.sustained))
+    }
+}
 
-const MACHINEGUN_DEFAULT_FIRERATE: u32 = 11;
-const MACHINEGUN_MAX_FIRERATE: u32 = 6;
-const MACHINEGUN_MAX_WIDTH: u32 = 3;
+// -- Shared state -------------------------------------------------------------
 
+/// State every weapon kind has: its definition, progression, and current stats.
 #[derive(Debug, Clone, PartialEq)]
-pub struct MachineGun {
+pub struct WeaponCore {
+    def: WeaponDef,
     level: u32,
-    fire_rate: u32,
-    fire_offset: f32,
-    bullet_speed: f32,
-    bullet_damage: f32,
-    pattern_width: u32,
+    experience: f32,
+    stats: WeaponStats,
+    heat: Option<Heat>,
 }
 
-impl MachineGun {
-    pub fn new() -> Self {
+impl WeaponCore {
+    pub fn new(def: &WeaponDef) -> Self {
         Self {
+            def: def.clone(),
             level: 0,
-            fire_rate: MACHINEGUN_DEFAULT_FIRERATE,
-            fire_offset: 3.0,
-            bullet_speed: 5.0,
-            bullet_damage: 4.0,
-            pattern_width: 0,
+            experience: 0.0,
+            stats: def.stats.clone(),
+            heat: def.heat.clone().map(Heat::new),
+        }
+    }
+
+    fn level_up(&mut self) {
+        self.level += 1;
+        for upgrade in self.def.upgrades.iter().filter(|u| u.applies_at(self.level)) {
+            self.stats.upgrade(upgrade);
+        }
+    }
+
+    fn fire_rate(&self) -> FireRate {
+        let burst = self.stats.fire_rate;
+        let sustained = match &self.heat {
+            //at equilibrium each shot's heat has to be shed before the next one
+            Some(heat) => burst.max((heat.def.per_shot / heat.def.dissipation).ceil() as u32),
+            None => burst,
+        };
+        FireRate { burst, sustained }
+    }
+}
+
+/// Heat builds with every shot and bleeds off over time. Reaching `capacity` overheats the
+/// weapon, locking it until it has cooled all the way down.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HeatDef {
+    pub per_shot: f32,
+    //heat lost per tick
+    pub dissipation: f32,
+    pub capacity: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heat {
+    def: HeatDef,
+    pub amount: f32,
+    pub overheated: bool,
+}
+
+impl Heat {
+    pub fn new(def: HeatDef) -> Self {
+        Self { def, amount: 0.0, overheated: false }
+    }
+    pub fn fraction(&self) -> f32 {
+        (self.amount / self.def.capacity).min(1.0)
+    }
+    fn add_shot(&mut self) {
+        self.amount += self.def.per_shot;
+        if self.amount >= self.def.capacity {
+            self.overheated = true;
+        }
+    }
+    fn cool(&mut self) {
+        self.amount = (self.amount - self.def.dissipation).max(0.0);
+        if self.amount == 0.0 {
+            self.overheated = false;
+        }
+    }
+}
+
+// -- Definitions --------------------------------------------------------------
+
+/// A weapon as described in `resources/weapons.json`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WeaponDef {
+    pub name: String,
+    pub bullet_type: BulletType,
+    pub kind: WeaponKind,
+    pub stats: WeaponStats,
+    #[serde(default)]
+    pub upgrades: Vec<Upgrade>,
+    #[serde(default)]
+    pub heat: Option<HeatDef>,
+}
+
+impl WeaponDef {
+    //a plasma cannon that could never grow its shot, and heat that never builds to a limit or cools
+    pub fn validate(&self) -> Result<(), String> {
+        if matches!(self.kind, WeaponKind::Plasma { ticks_per_size: 0 }) {
+            return Err(format!("{}: ticks_per_size must be at least 1", self.name));
+        }
+        if let Some(heat) = &self.heat {
+            if heat.capacity <= 0.0 || heat.dissipation <= 0.0 {
+                return Err(format!("{}: heat capacity and dissipation must be positive", self.name));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Which kind of weapon a definition builds, along with settings specific to that kind.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum WeaponKind {
+    Gun { pattern: FirePattern },
+    //holding fire for `ticks_per_size` grows the shot by one `BulletSize`
+    Plasma { ticks_per_size: u32 },
+    //missiles leave `launch_spread` radians apart and turn at most `turn_rate` radians per tick
+    Homing { turn_rate: f32, launch_spread: f32 },
+    //a beam `width` wide reaching up to `range`, dealing `bullet_damage` every tick
+    Beam { range: f32, width: f32 },
+}
+
+/// How a single volley is laid out. `width` bullets are added on each side of the center one.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum FirePattern {
+    //parallel bullets, outer ones trailing slightly behind
+    Row { spacing: f32, stagger: f32 },
+    //bullets fanned out sideways, outer ones trailing quadratically
+    Fan { spacing: f32, curvature: f32, spread: f32 },
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WeaponStats {
+    //ticks between volleys
+    pub fire_rate: u32,
+    pub fire_offset: f32,
+    pub bullet_speed: f32,
+    pub bullet_damage: f32,
+    #[serde(default)]
+    pub bullet_size: BulletSize,
+    pub width: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stat {
+    FireRate,
+    BulletSpeed,
+    BulletDamage,
+    BulletSize,
+    Width,
+}
+
+/// Changes `stat` by `change` at `from_level` and every `every` levels after, never passing `limit`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Upgrade {
+    pub stat: Stat,
+    pub from_level: u32,
+    pub every: u32,
+    pub change: f32,
+    pub limit: f32,
+}
+
+impl Upgrade {
+    fn applies_at(&self, level: u32) -> bool {
+        level >= self.from_level && (level - self.from_level).is_multiple_of(self.every.max(1))
+    }
+    fn apply(&self, value: f32) -> f32 {
+        if self.change < 0.0 {
+            (value + self.change).max(self.limit)
+        } else {
+            (value + self.change).min(self.limit)
+        }
+    }
+}
+
+impl WeaponStats {
+    fn upgrade(&mut self, upgrade: &Upgrade) {
+        match upgrade.stat {
+            Stat::FireRate => self.fire_rate = upgrade.apply(self.fire_rate as f32) as u32,
+            Stat::BulletSpeed => self.bullet_speed = upgrade.apply(self.bullet_speed),
+            Stat::BulletDamage => self.bullet_damage = upgrade.apply(self.bullet_damage),
+            Stat::BulletSize => {
+                self.bullet_size = BulletSize::from_index(upgrade.apply(self.bullet_size.index() as f32) as u32)
+            }
+            Stat::Width => self.width = upgrade.apply(self.width as f32) as u32,
+        }
+    }
+}
+
+// -- Gun ----------------------------------------------------------------------
+
+/// A weapon that fires a volley of bullets in a `FirePattern`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gun {
+    core: WeaponCore,
+    pattern: FirePattern,
+}
+
+impl Gun {
+    pub fn new(def: &WeaponDef, pattern: FirePattern) -> Self {
+        Self {
+            core: WeaponCore::new(def),
+            pattern,
         }
     }
 }
 
-impl WeaponBehavior for MachineGun {
+impl WeaponBehavior for Gun {
+    fn core(&self) -> &WeaponCore { &self.core }
+    fn core_mut(&mut self) -> &mut WeaponCore { &mut self.core }
+
     fn fire(&self, shooter: &dyn GameObject) -> Vec<Bullet> {
-        let velocity = Vec2::new(0.0, -self.bullet_speed);
-        let w = self.pattern_width as i32;
+        let stats = &self.core.stats;
+        let w = stats.width as i32;
         (-w..=w)
             .map(|i| {
                 let n = i as f32;
-                let offset = Vec2::new(n * 10.0, n.abs() * 2.5 - 1.0 + self.fire_offset);
-                Bullet::new(shooter, velocity, Some(offset), self.bullet_damage, BulletType::Minigun)
+                let (velocity, offset) = match self.pattern {
+                    FirePattern::Row { spacing, stagger } => (
+                        Vec2::new(0.0, -stats.bullet_speed),
+                        Vec2::new(n * spacing, n.abs() * stagger - 1.0 + stats.fire_offset),
+                    ),
+                    FirePattern::Fan { spacing, curvature, spread } => (
+                        Vec2::new(n * spread, -stats.bullet_speed),
+                        Vec2::new(n * spacing, n.powi(2) * curvature - 1.0 + stats.fire_offset),
+                    ),
+                };
+                Bullet::new(shooter, velocity, Some(offset), stats.bullet_damage, self.core.def.bullet_type.clone())
+                    .with_size(stats.bullet_size)
             })
             .collect()
     }
+}
 
-    fn fire_rate(&self) -> u32 { self.fire_rate }
-    fn level(&self) -> u32 { self.level }
-    fn name(&self) -> &'static str { "MachineGun" }
+// -- PlasmaCannon -------------------------------------------------------------
 
-    fn level_up(&mut self) {
-        self.level += 1;
-        if self.level > 1 && (self.level - 2) % 4 == 0 {
-            self.pattern_width = (self.pattern_width + 1).min(MACHINEGUN_MAX_WIDTH);
-        }
-        if self.level % 2 == 0 {
-            self.fire_rate = (self.fire_rate - 1).max(MACHINEGUN_MAX_FIRERATE);
+/// A slow cannon that charges while fire is held, releasing a single shot
+/// that grows from `Plasma_Small` to `Plasma_Large` the longer it was charged.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlasmaCannon {
+    core: WeaponCore,
+    ticks_per_size: u32,
+    charge: u32,
+}
+
+impl PlasmaCannon {
+    pub fn new(def: &WeaponDef, ticks_per_size: u32) -> Self {
+        Self {
+            core: WeaponCore::new(def),
+            ticks_per_size,
+            charge: 0,
         }
     }
+
+    fn max_charge(&self) -> u32 {
+        2 * self.ticks_per_size
+    }
+
+    //each `ticks_per_size` of charge grows the shot one tier past its upgraded base size
+    fn bullet_size(&self) -> BulletSize {
+        BulletSize::from_index(self.core.stats.bullet_size.index() + self.charge / self.ticks_per_size)
+    }
 }
 
-// -- WideGun ------------------------------------------------------------------
+impl WeaponBehavior for PlasmaCannon {
+    fn core(&self) -> &WeaponCore { &self.core }
+    fn core_mut(&mut self) -> &mut WeaponCore { &mut self.core }
 
-const WIDEGUN_DEFAULT_FIRERATE: u32 = 14;
-const WIDEGUN_MAX_FIRERATE: u32 = 10;
-const WIDEGUN_MAX_WIDTH: u32 = 5;
+    fn fire(&self, shooter: &dyn GameObject) -> Vec<Bullet> {
+        let velocity = Vec2::new(0.0, -self.core.stats.bullet_speed);
+        let offset = Vec2::new(0.0, self.core.stats.fire_offset);
+        vec![
+            Bullet::new(shooter, velocity, Some(offset), self.core.stats.bullet_damage, self.core.def.bullet_type.clone())
+                .with_size(self.bullet_size()),
+        ]
+    }
+
+    fn fire_mode(&self) -> FireMode { FireMode::Charge }
+    fn charge(&mut self) {
+        self.charge = (self.charge + 1).min(self.max_charge());
+    }
+    fn charge_fraction(&self) -> f32 {
+        self.charge as f32 / self.max_charge() as f32
+    }
+    fn discharge(&mut self) {
+        self.charge = 0;
+    }
 
+    fn info(&self) -> String {
+        format!("{} ( level: {}, charge: {:.0}% )", self.name(), self.level(), 100.0 * self.charge_fraction())
+    }
+}
+
+// -- MissileLauncher ----------------------------------------------------------
+
+/// Fires missiles that fan out, then lock on to the nearest enemy and steer toward it.
 #[derive(Debug, Clone, PartialEq)]
-pub struct WideGun {
-    level: u32,
-    fire_rate: u32,
-    num_bullets: u32,
-    fire_offset: f32,
-    bullet_speed: f32,
-    bullet_damage: f32,
+pub struct MissileLauncher {
+    core: WeaponCore,
+    turn_rate: f32,
+    launch_spread: f32,
 }
 
-impl WideGun {
-    pub fn new() -> Self {
+impl MissileLauncher {
+    pub fn new(def: &WeaponDef, turn_rate: f32, launch_spread: f32) -> Self {
         Self {
-            level: 0,
-            fire_rate: WIDEGUN_DEFAULT_FIRERATE,
-            num_bullets: 1,
-            fire_offset: 3.0,
-            bullet_speed: 4.5,
-            bullet_damage: 3.0,
+            core: WeaponCore::new(def),
+            turn_rate,
+            launch_spread,
         }
     }
 }
 
-impl WeaponBehavior for WideGun {
+impl WeaponBehavior for MissileLauncher {
+    fn core(&self) -> &WeaponCore { &self.core }
+    fn core_mut(&mut self) -> &mut WeaponCore { &mut self.core }
+
     fn fire(&self, shooter: &dyn GameObject) -> Vec<Bullet> {
-        let n = self.num_bullets as i32;
-        (-n..=n)
+        let stats = &self.core.stats;
+        let w = stats.width as i32;
+        (-w..=w)
             .map(|i| {
-                let f = i as f32;
-                let velocity = Vec2::new(f * 0.4, -self.bullet_speed);
-                let offset = Vec2::new(f * 10.0, f.powi(2) * 1.5 - 1.0 + self.fire_offset);
-                Bullet::new(shooter, velocity, Some(offset), self.bullet_damage, BulletType::Laser)
+                let n = i as f32;
+                let heading = n * self.launch_spread;
+                let velocity = Vec2::new(heading.sin(), -heading.cos()) * stats.bullet_speed;
+                let offset = Vec2::new(n * 12.0, stats.fire_offset);
+                Bullet::new(shooter, velocity, Some(offset), stats.bullet_damage, self.core.def.bullet_type.clone())
+                    .with_size(stats.bullet_size)
+                    .with_homing(self.turn_rate)
             })
             .collect()
     }
+}
 
-    fn fire_rate(&self) -> u32 { self.fire_rate }
-    fn level(&self) -> u32 { self.level }
-    fn name(&self) -> &'static str { "WideGun" }
+// -- BeamLaser ----------------------------------------------------------------
 
-    fn level_up(&mut self) {
-        self.level += 1;
-        if self.level > 1 && (self.level - 2) % 4 == 0 {
-            self.num_bullets = (self.num_bullets + 1).min(WIDEGUN_MAX_WIDTH);
-        }
-        if self.level % 2 == 0 {
-            self.fire_rate = (self.fire_rate - 1).max(WIDEGUN_MAX_FIRERATE);
+/// A laser that stays on while fire is held, damaging the first enemy in its path every tick.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BeamLaser {
+    core: WeaponCore,
+    range: f32,
+    width: f32,
+}
+
+impl BeamLaser {
+    pub fn new(def: &WeaponDef, range: f32, width: f32) -> Self {
+        Self {
+            core: WeaponCore::new(def),
+            range,
+            width,
         }
     }
 }
+
+impl WeaponBehavior for BeamLaser {
+    fn core(&self) -> &WeaponCore { &self.core }
+    fn core_mut(&mut self) -> &mut WeaponCore { &mut self.core }
+
+    fn fire(&self, _shooter: &dyn GameObject) -> Vec<Bullet> { Vec::new() }
+
+    fn fire_mode(&self) -> FireMode { FireMode::Beam }
+    fn beam(&self, shooter: &dyn GameObject) -> Option<Beam> {
+        let origin = shooter.position() + Vec2::new(shooter.size().x / 2.0, self.core.stats.fire_offset);
+        Some(Beam::new(origin, self.range, self.width, self.core.stats.bullet_damage))
+    }
+}
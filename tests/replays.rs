@@ -0,0 +1,13 @@
+use std::path::Path;
+
+use rust_shooter::data::GameData;
+use rust_shooter::replay::verify_replays;
+
+//every recorded fixture must still reach its blessed score and stage
+#[test]
+fn replays_match_their_recorded_results() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let data = GameData::load(&root.join("resources")).expect("game data should load");
+    let failures = verify_replays(&root.join("replays"), &data, false).expect("replays should be readable");
+    assert_eq!(failures, 0, "{failures} replay(s) diverged; rerun with --verify-replays replays --bless if intended");
+}